@@ -0,0 +1,35 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use res_regex::RegexParser;
+
+/// A pattern with many alternatives, optionally starting
+/// with a named group
+fn alternation(named: bool) -> String {
+    let alternatives: Vec<String> = (0..200).map(|i| format!("word{}[a-z]+", i)).collect();
+    let group = if named { "(?<first>x)" } else { "(x)" };
+    format!("/{}|{}/", group, alternatives.join("|"))
+}
+
+fn named_groups(c: &mut Criterion) {
+    let mut group = c.benchmark_group("alternation");
+    for (name, named) in &[("unnamed", false), ("named", true)] {
+        let re = alternation(*named);
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                let mut parser = RegexParser::new(black_box(&re)).unwrap();
+                parser.validate().unwrap();
+            })
+        });
+    }
+    // a `\k` means the named mode pass can't be skipped
+    let re = format!("{}\\k<first>/", alternation(true).trim_end_matches('/'));
+    group.bench_function("named_with_reference", |b| {
+        b.iter(|| {
+            let mut parser = RegexParser::new(black_box(&re)).unwrap();
+            parser.validate().unwrap();
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, named_groups);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use res_regex::RegexParser;
+
+const PATTERNS: &[(&str, &str)] = &[
+    (r"^(?:a|b)*c[d-f]+$", ""),
+    (r"(?<year>\d{4})-(?<month>\d{2})-(?<day>\d{2})", ""),
+    (r"\p{Script=Greek}+\u{1F600}", "u"),
+    (r"[\w--\d]+", "v"),
+    (r"(a)\1|\k<x>", "gi"),
+];
+
+fn reset_with(c: &mut Criterion) {
+    let mut group = c.benchmark_group("many_patterns");
+    group.bench_function("new_parser", |b| {
+        b.iter(|| {
+            for (pattern, flags) in PATTERNS {
+                let mut parser =
+                    RegexParser::from_parts(black_box(pattern), black_box(flags)).unwrap();
+                parser.validate().unwrap();
+            }
+        })
+    });
+    group.bench_function("reset_with", |b| {
+        let mut parser = RegexParser::from_parts("", "").unwrap();
+        b.iter(|| {
+            for (pattern, flags) in PATTERNS {
+                parser
+                    .reset_with(black_box(pattern), black_box(flags))
+                    .unwrap();
+                parser.validate().unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, reset_with);
+criterion_main!(benches);
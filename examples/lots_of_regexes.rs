@@ -6,7 +6,7 @@ fn main() {
         let mut parser = RegexParser::new(regex).expect("unable to contruct the parser");
         parser
             .validate()
-            .expect(&format!("{}: failed to parse {}", i, regex));
+            .unwrap_or_else(|e| panic!("{}: failed to parse {}: {}", i, regex, e));
     }
 }
 
@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use res_regex::RegexParser;
+
+// Splitting a literal into its pattern and flags, validating it
+// and building its tree should never panic, no matter how malformed
+// the input is. Invalid UTF-8 is replaced so multi byte characters
+// end up next to the `/`s
+fuzz_target!(|data: &[u8]| {
+    let js = String::from_utf8_lossy(data);
+    if let Ok(mut parser) = RegexParser::new(&js) {
+        let _ = parser.parse();
+    }
+});
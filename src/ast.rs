@@ -0,0 +1,1202 @@
+//! A tree representation of a regular expression pattern
+//!
+//! Every node carries the byte span `(start, end)` it covers
+//! in the pattern (the text between the `/`s). The tree is
+//! produced by `RegexParser::parse` and the `Display` impls
+//! will print a node back out as regular expression source.
+//!
+//! With the `serde` feature every node can be serialized,
+//! spans are written as a 2 element array.
+use crate::{Error, ErrorKind, EscapeInfo, QuantifierInfo, Token, TokenKind};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::{Display, Formatter, Result as FmtResult};
+
+pub type Span = (usize, usize);
+
+/// The root of a pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pattern {
+    pub span: Span,
+    pub disjunction: Disjunction,
+}
+
+/// One or more alternatives separated by `|`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Disjunction {
+    pub span: Span,
+    pub alternatives: Vec<Alternative>,
+}
+
+/// A sequence of terms, this may be empty
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Alternative {
+    pub span: Span,
+    pub terms: Vec<Term>,
+}
+
+/// An assertion or atom with an optional quantifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Term {
+    pub span: Span,
+    pub kind: TermKind,
+    pub quantifier: Option<Quantifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TermKind {
+    Assertion(Assertion),
+    Atom(Atom),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Assertion {
+    pub span: Span,
+    pub kind: AssertionKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AssertionKind {
+    /// `^`
+    Start,
+    /// `$`
+    End,
+    /// `\b`
+    WordBoundary,
+    /// `\B`
+    NotWordBoundary,
+    /// `(?=...)` or `(?!...)`
+    Lookahead {
+        negated: bool,
+        disjunction: Disjunction,
+    },
+    /// `(?<=...)` or `(?<!...)`
+    Lookbehind {
+        negated: bool,
+        disjunction: Disjunction,
+    },
+}
+
+/// `*`, `+`, `?` or `{min,max}` with an optional trailing `?`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quantifier {
+    pub span: Span,
+    /// The minimum number of repetitions, a braced bound
+    /// is exactly as written (clamped to `u64::MAX`)
+    pub min: u64,
+    /// The maximum number of repetitions, `None` when unbounded
+    pub max: Option<u64>,
+    /// `false` when followed by a `?` (lazy)
+    pub greedy: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Atom {
+    /// A character that matches itself
+    Char(Char),
+    /// `.`
+    Dot(Span),
+    Escape(Escape),
+    BackReference(BackReference),
+    Class(CharacterClass),
+    Group(Group),
+}
+
+/// A literal character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Char {
+    pub span: Span,
+    pub value: char,
+}
+
+/// Any escape sequence that isn't a back reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Escape {
+    pub span: Span,
+    pub kind: EscapeKind,
+    /// The escape as it appeared in the pattern
+    pub raw: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EscapeKind {
+    /// An escape that represents a single code point
+    /// like `\n`, `\x41` or `\u{1F600}`
+    Character(u32),
+    /// `\d`, `\D`, `\s`, `\S`, `\w` or `\W`
+    Class(char),
+    /// `\p{...}` or `\P{...}`
+    Property {
+        negated: bool,
+        name: Option<String>,
+        value: String,
+    },
+}
+
+/// `\1` or `\k<name>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BackReference {
+    pub span: Span,
+    pub kind: BackReferenceKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BackReferenceKind {
+    Numeric(u32),
+    Named(String),
+}
+
+/// `[...]` or `[^...]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CharacterClass {
+    pub span: Span,
+    pub negated: bool,
+    /// How the items are combined, this is always `Union`
+    /// without the `v` flag
+    pub operation: ClassOperation,
+    pub items: Vec<ClassItem>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClassOperation {
+    /// `[ab]`
+    Union,
+    /// `[a&&b]`
+    Intersection,
+    /// `[a--b]`
+    Subtraction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClassItem {
+    Atom(ClassAtom),
+    /// `a-z`
+    Range {
+        span: Span,
+        start: ClassAtom,
+        end: ClassAtom,
+    },
+    /// A class nested inside of another, only with the `v` flag
+    Class(CharacterClass),
+    /// `\q{abc|d}`, only with the `v` flag. Each alternative
+    /// is kept as it was written, including any escapes
+    Strings {
+        span: Span,
+        strings: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClassAtom {
+    Char(Char),
+    Escape(Escape),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Group {
+    pub span: Span,
+    pub kind: GroupKind,
+    pub disjunction: Disjunction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GroupKind {
+    /// `(...)` or `(?<name>...)`, `index` is the
+    /// 1 based number used by back references
+    Capturing { index: u32, name: Option<String> },
+    /// `(?:...)`
+    NonCapturing,
+    /// `(?i-m:...)`, a non-capturing group that adds
+    /// and removes the `i`, `m` or `s` flags inside it
+    Modifiers { add: String, remove: String },
+}
+
+/// Renumber the capturing groups in a pattern after it has
+/// been modified, numeric back references are updated to
+/// follow the group they referred to. If a back reference
+/// points to a group that no longer exists an error is returned
+///
+/// ```
+/// use res_regex::{ast, RegexParser};
+/// let mut parser = RegexParser::new(r"/(a)(b)\2/").unwrap();
+/// let mut pattern = parser.parse().unwrap();
+/// pattern.disjunction.alternatives[0].terms.remove(0);
+/// ast::renumber_backreferences(&mut pattern).unwrap();
+/// assert_eq!(pattern.to_string(), r"(b)\1");
+/// ```
+pub fn renumber_backreferences(pattern: &mut Pattern) -> Result<(), Error> {
+    let mut map = Vec::new();
+    number_groups(&mut pattern.disjunction, &mut map);
+    update_back_refs(&mut pattern.disjunction, &map)
+}
+
+fn number_groups(disjunction: &mut Disjunction, map: &mut Vec<(u32, u32)>) {
+    for alt in &mut disjunction.alternatives {
+        for term in &mut alt.terms {
+            match &mut term.kind {
+                TermKind::Atom(Atom::Group(group)) => {
+                    if let GroupKind::Capturing { index, .. } = &mut group.kind {
+                        let new = map.len() as u32 + 1;
+                        map.push((*index, new));
+                        *index = new;
+                    }
+                    number_groups(&mut group.disjunction, map);
+                }
+                TermKind::Assertion(Assertion {
+                    kind: AssertionKind::Lookahead { disjunction, .. },
+                    ..
+                })
+                | TermKind::Assertion(Assertion {
+                    kind: AssertionKind::Lookbehind { disjunction, .. },
+                    ..
+                }) => number_groups(disjunction, map),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn update_back_refs(disjunction: &mut Disjunction, map: &[(u32, u32)]) -> Result<(), Error> {
+    for alt in &mut disjunction.alternatives {
+        for term in &mut alt.terms {
+            match &mut term.kind {
+                TermKind::Atom(Atom::BackReference(BackReference {
+                    span,
+                    kind: BackReferenceKind::Numeric(n),
+                })) => {
+                    if let Some((_, new)) = map.iter().find(|(old, _)| old == n) {
+                        *n = *new;
+                    } else {
+                        return Err(Error::new(
+                            span.0,
+                            ErrorKind::InvalidBackReference,
+                            &format!("Back reference to a group that no longer exists (\\{})", n),
+                        ));
+                    }
+                }
+                TermKind::Atom(Atom::Group(group)) => {
+                    update_back_refs(&mut group.disjunction, map)?
+                }
+                TermKind::Assertion(Assertion {
+                    kind: AssertionKind::Lookahead { disjunction, .. },
+                    ..
+                })
+                | TermKind::Assertion(Assertion {
+                    kind: AssertionKind::Lookbehind { disjunction, .. },
+                    ..
+                }) => update_back_refs(disjunction, map)?,
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Pattern {
+    /// A plain English explanation of the pattern
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/ab+c/").unwrap();
+    /// let pattern = parser.parse().unwrap();
+    /// assert_eq!(pattern.describe(), "'a', then one or more 'b', then 'c'");
+    /// ```
+    pub fn describe(&self) -> String {
+        describe_disjunction(&self.disjunction)
+    }
+    /// The fewest characters a match can consume, `None` if
+    /// the pattern can never match
+    pub(crate) fn min_len(&self) -> Option<usize> {
+        disjunction_min_len(&self.disjunction)
+    }
+    /// The fixed strings that a match must start with, one for
+    /// each alternative, `None` if any alternative could start
+    /// with something other than a literal
+    pub(crate) fn literal_prefixes(&self) -> Option<Vec<String>> {
+        let mut ret = Vec::new();
+        for alternative in &self.disjunction.alternatives {
+            for prefix in alternative_prefixes(&alternative.terms).0 {
+                if prefix.is_empty() {
+                    return None;
+                }
+                if !ret.contains(&prefix) {
+                    ret.push(prefix);
+                }
+            }
+        }
+        Some(ret)
+    }
+}
+
+/// The leading literal text of a sequence of terms along with
+/// `true` if the whole sequence was literal. A group with more
+/// than one alternative ends the prefix, extending past it would
+/// multiply the number of prefixes
+fn alternative_prefixes(terms: &[Term]) -> (Vec<String>, bool) {
+    let mut prefixes = vec![String::new()];
+    for term in terms {
+        let atom = match &term.kind {
+            TermKind::Assertion(Assertion {
+                kind: AssertionKind::Lookahead { .. } | AssertionKind::Lookbehind { .. },
+                ..
+            }) => return (prefixes, false),
+            // the other assertions don't consume anything
+            TermKind::Assertion(_) => continue,
+            TermKind::Atom(atom) => atom,
+        };
+        if let Some(ch) = literal_char(atom) {
+            for prefix in &mut prefixes {
+                prefix.push(ch);
+            }
+            match &term.quantifier {
+                None => continue,
+                Some(quantifier) if quantifier.min > 0 => return (prefixes, false),
+                Some(_) => {
+                    for prefix in &mut prefixes {
+                        prefix.pop();
+                    }
+                    return (prefixes, false);
+                }
+            }
+        }
+        let group = match (atom, &term.quantifier) {
+            (Atom::Group(group), None) => match &group.kind {
+                // the prefixes are case sensitive
+                GroupKind::Modifiers { add, .. } if add.contains('i') => return (prefixes, false),
+                _ => group,
+            },
+            _ => return (prefixes, false),
+        };
+        let mut complete = group.disjunction.alternatives.len() == 1;
+        let mut next = Vec::new();
+        for alternative in &group.disjunction.alternatives {
+            let (alternative_prefixes, alternative_complete) =
+                alternative_prefixes(&alternative.terms);
+            complete &= alternative_complete;
+            next.extend(alternative_prefixes);
+        }
+        prefixes = prefixes
+            .iter()
+            .flat_map(|prefix| next.iter().map(move |n| format!("{}{}", prefix, n)))
+            .collect();
+        if !complete {
+            return (prefixes, false);
+        }
+    }
+    (prefixes, true)
+}
+
+/// The character an atom matches if it only matches one
+fn literal_char(atom: &Atom) -> Option<char> {
+    match atom {
+        Atom::Char(c) => Some(c.value),
+        Atom::Escape(Escape {
+            kind: EscapeKind::Character(value),
+            ..
+        }) => core::char::from_u32(*value),
+        _ => None,
+    }
+}
+
+/// The fewest characters that can match a disjunction, `None`
+/// if none of its alternatives can match
+fn disjunction_min_len(disjunction: &Disjunction) -> Option<usize> {
+    disjunction
+        .alternatives
+        .iter()
+        .filter_map(|alternative| {
+            alternative.terms.iter().try_fold(0usize, |len, term| {
+                Some(len.saturating_add(term_min_len(term)?))
+            })
+        })
+        .min()
+}
+
+/// A class matches a single character unless it has `\q{...}`
+/// strings, those aren't decoded so they count as possibly empty
+fn class_min_len(class: &CharacterClass) -> Option<usize> {
+    if !class.negated && class.items.is_empty() {
+        return None;
+    }
+    let may_be_empty = class.items.iter().any(|item| match item {
+        ClassItem::Strings { .. } => true,
+        ClassItem::Class(class) => class_min_len(class) == Some(0),
+        _ => false,
+    });
+    Some(if may_be_empty { 0 } else { 1 })
+}
+
+fn term_min_len(term: &Term) -> Option<usize> {
+    let atom = match &term.kind {
+        TermKind::Assertion(_) => return Some(0),
+        TermKind::Atom(atom) => atom,
+    };
+    let len = match atom {
+        Atom::Group(group) => disjunction_min_len(&group.disjunction),
+        // the group being referenced may not have matched
+        Atom::BackReference(_) => Some(0),
+        Atom::Class(class) => class_min_len(class),
+        _ => Some(1),
+    };
+    match &term.quantifier {
+        Some(quantifier) if quantifier.min == 0 => Some(0),
+        Some(quantifier) => len.map(|len| len.saturating_mul(quantifier.min as usize)),
+        None => len,
+    }
+}
+
+fn describe_disjunction(disjunction: &Disjunction) -> String {
+    if disjunction.alternatives.len() == 1 {
+        return describe_alternative(&disjunction.alternatives[0]);
+    }
+    let alts: Vec<String> = disjunction
+        .alternatives
+        .iter()
+        .map(|alt| format!("({})", describe_alternative(alt)))
+        .collect();
+    format!("either {}", alts.join(" or "))
+}
+
+fn describe_alternative(alternative: &Alternative) -> String {
+    if alternative.terms.is_empty() {
+        return "nothing".to_string();
+    }
+    let terms: Vec<String> = alternative.terms.iter().map(describe_term).collect();
+    terms.join(", then ")
+}
+
+fn describe_term(term: &Term) -> String {
+    let item = match &term.kind {
+        TermKind::Assertion(assertion) => describe_assertion(assertion),
+        TermKind::Atom(atom) => describe_atom(atom),
+    };
+    let quantifier = if let Some(quantifier) = &term.quantifier {
+        quantifier
+    } else {
+        return item;
+    };
+    let mut ret = match (quantifier.min, quantifier.max) {
+        (0, None) => format!("zero or more {}", item),
+        (1, None) => format!("one or more {}", item),
+        (0, Some(1)) => format!("optionally {}", item),
+        (min, None) => format!("at least {} of {}", min, item),
+        (min, Some(max)) if min == max => format!("exactly {} of {}", min, item),
+        (min, Some(max)) => format!("between {} and {} of {}", min, max, item),
+    };
+    if !quantifier.greedy {
+        ret.push_str(" (as few as possible)");
+    }
+    ret
+}
+
+fn describe_assertion(assertion: &Assertion) -> String {
+    match &assertion.kind {
+        AssertionKind::Start => "the start of the input".to_string(),
+        AssertionKind::End => "the end of the input".to_string(),
+        AssertionKind::WordBoundary => "a word boundary".to_string(),
+        AssertionKind::NotWordBoundary => "not a word boundary".to_string(),
+        AssertionKind::Lookahead {
+            negated,
+            disjunction,
+        } => format!(
+            "{}followed by ({})",
+            if *negated { "not " } else { "" },
+            describe_disjunction(disjunction)
+        ),
+        AssertionKind::Lookbehind {
+            negated,
+            disjunction,
+        } => format!(
+            "{}preceded by ({})",
+            if *negated { "not " } else { "" },
+            describe_disjunction(disjunction)
+        ),
+    }
+}
+
+fn describe_atom(atom: &Atom) -> String {
+    match atom {
+        Atom::Char(ch) => describe_char(ch.value as u32),
+        Atom::Dot(_) => "any character".to_string(),
+        Atom::Escape(escape) => describe_escape(escape),
+        Atom::BackReference(BackReference {
+            kind: BackReferenceKind::Numeric(n),
+            ..
+        }) => format!("the same text as group {}", n),
+        Atom::BackReference(BackReference {
+            kind: BackReferenceKind::Named(name),
+            ..
+        }) => format!("the same text as group '{}'", name),
+        Atom::Class(class) => describe_class(class),
+        Atom::Group(group) => match &group.kind {
+            GroupKind::Capturing { index, name: None } => format!(
+                "group {} ({})",
+                index,
+                describe_disjunction(&group.disjunction)
+            ),
+            GroupKind::Capturing {
+                name: Some(name), ..
+            } => format!(
+                "group '{}' ({})",
+                name,
+                describe_disjunction(&group.disjunction)
+            ),
+            GroupKind::NonCapturing => format!("({})", describe_disjunction(&group.disjunction)),
+            GroupKind::Modifiers { add, remove } => {
+                let mut flags = Vec::new();
+                if !add.is_empty() {
+                    flags.push(format!("with the {} flag", add));
+                }
+                if !remove.is_empty() {
+                    flags.push(format!("without the {} flag", remove));
+                }
+                format!(
+                    "({} {})",
+                    flags.join(" and "),
+                    describe_disjunction(&group.disjunction)
+                )
+            }
+        },
+    }
+}
+
+fn describe_class(class: &CharacterClass) -> String {
+    let items: Vec<String> = class
+        .items
+        .iter()
+        .map(|item| match item {
+            ClassItem::Atom(atom) => describe_class_atom(atom),
+            ClassItem::Range { start, end, .. } => format!(
+                "{} to {}",
+                describe_class_atom(start),
+                describe_class_atom(end)
+            ),
+            ClassItem::Class(class) => format!("({})", describe_class(class)),
+            ClassItem::Strings { strings, .. } => {
+                let strings: Vec<String> = strings.iter().map(|s| format!("'{}'", s)).collect();
+                format!("the strings {}", strings.join(", "))
+            }
+        })
+        .collect();
+    let set = match class.operation {
+        ClassOperation::Union => {
+            return match (class.negated, items.is_empty()) {
+                (false, true) => "nothing (an empty class never matches)".to_string(),
+                (true, true) => "any character".to_string(),
+                (false, false) => format!("any of {}", items.join(", ")),
+                (true, false) => format!("none of {}", items.join(", ")),
+            }
+        }
+        ClassOperation::Intersection => format!("anything in both {}", items.join(" and ")),
+        ClassOperation::Subtraction => format!("{} but not {}", items[0], items[1..].join(" or ")),
+    };
+    if class.negated {
+        format!("anything except ({})", set)
+    } else {
+        set
+    }
+}
+
+fn describe_class_atom(atom: &ClassAtom) -> String {
+    match atom {
+        ClassAtom::Char(ch) => describe_char(ch.value as u32),
+        ClassAtom::Escape(escape) => describe_escape(escape),
+    }
+}
+
+fn describe_escape(escape: &Escape) -> String {
+    match &escape.kind {
+        EscapeKind::Character(value) => describe_char(*value),
+        EscapeKind::Class(class) => match class {
+            'd' => "a digit",
+            'D' => "a non-digit",
+            'w' => "a word character",
+            'W' => "a non-word character",
+            's' => "whitespace",
+            _ => "a non-whitespace character",
+        }
+        .to_string(),
+        EscapeKind::Property {
+            negated,
+            name,
+            value,
+        } => {
+            let property = if let Some(name) = name {
+                format!("{}={}", name, value)
+            } else {
+                value.to_string()
+            };
+            format!(
+                "a character {} the property {}",
+                if *negated { "without" } else { "with" },
+                property
+            )
+        }
+    }
+}
+
+fn describe_char(value: u32) -> String {
+    match core::char::from_u32(value) {
+        Some(ch) if !ch.is_control() && !ch.is_whitespace() => format!("'{}'", ch),
+        Some(' ') => "a space".to_string(),
+        _ => format!("U+{:04X}", value),
+    }
+}
+
+/// Builds the tree from the tokens recorded while validating
+pub(crate) struct Builder<'a> {
+    pattern: &'a str,
+    tokens: &'a [Token],
+    quantifiers: &'a [QuantifierInfo],
+    escapes: &'a [EscapeInfo],
+    idx: usize,
+    cursor: usize,
+    group_count: u32,
+}
+
+impl<'a> Builder<'a> {
+    pub(crate) fn new(
+        pattern: &'a str,
+        tokens: &'a [Token],
+        quantifiers: &'a [QuantifierInfo],
+        escapes: &'a [EscapeInfo],
+    ) -> Self {
+        Self {
+            pattern,
+            tokens,
+            quantifiers,
+            escapes,
+            idx: 0,
+            cursor: 0,
+            group_count: 0,
+        }
+    }
+
+    /// The tokens come from a successful validation so they
+    /// should always form a tree, if they don't (a bug in the
+    /// parser) this is an `ErrorKind::Internal` error
+    pub(crate) fn build(mut self) -> Result<Pattern, Error> {
+        let disjunction = self.disjunction()?;
+        Ok(Pattern {
+            span: (0, self.pattern.len()),
+            disjunction,
+        })
+    }
+
+    fn unexpected(&self, msg: &str) -> Error {
+        Error::new(self.cursor, ErrorKind::Internal, msg)
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.idx)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.idx)?;
+        self.idx += 1;
+        self.cursor = token.span.1;
+        Some(token)
+    }
+
+    fn raw(&self, span: Span) -> &'a str {
+        &self.pattern[span.0..span.1]
+    }
+
+    fn disjunction(&mut self) -> Result<Disjunction, Error> {
+        let start = self.cursor;
+        let mut alternatives = vec![self.alternative()?];
+        while let Some(Token {
+            kind: TokenKind::Alternation,
+            ..
+        }) = self.peek()
+        {
+            self.next();
+            alternatives.push(self.alternative()?);
+        }
+        Ok(Disjunction {
+            span: (start, self.cursor),
+            alternatives,
+        })
+    }
+
+    fn alternative(&mut self) -> Result<Alternative, Error> {
+        let start = self.cursor;
+        let mut terms = Vec::new();
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::Alternation | TokenKind::GroupClose => break,
+                _ => terms.push(self.term()?),
+            }
+        }
+        Ok(Alternative {
+            span: (start, self.cursor),
+            terms,
+        })
+    }
+
+    fn term(&mut self) -> Result<Term, Error> {
+        let token = self
+            .next()
+            .ok_or_else(|| self.unexpected("Expected a term"))?;
+        let kind = match token.kind {
+            TokenKind::Literal => TermKind::Atom(Atom::Char(self.char(token.span))),
+            TokenKind::Dot => TermKind::Atom(Atom::Dot(token.span)),
+            TokenKind::Escape => TermKind::Atom(self.escape_atom(token.span)),
+            TokenKind::Assertion => TermKind::Assertion(Assertion {
+                span: token.span,
+                kind: match self.raw(token.span) {
+                    "^" => AssertionKind::Start,
+                    "$" => AssertionKind::End,
+                    "\\b" => AssertionKind::WordBoundary,
+                    _ => AssertionKind::NotWordBoundary,
+                },
+            }),
+            TokenKind::GroupOpen => self.group(token.span)?,
+            TokenKind::ClassOpen => TermKind::Atom(Atom::Class(self.class(token.span)?)),
+            _ => {
+                return Err(Error::new(
+                    token.span.0,
+                    ErrorKind::Internal,
+                    &format!("Unexpected {:?} token at the start of a term", token.kind),
+                ))
+            }
+        };
+        let start = token.span.0;
+        let quantifier = if let Some(Token {
+            kind: TokenKind::Quantifier,
+            ..
+        }) = self.peek()
+        {
+            let token = self
+                .next()
+                .ok_or_else(|| self.unexpected("Expected a quantifier"))?;
+            let info = self
+                .quantifiers
+                .iter()
+                .find(|q| q.at == token.span.0)
+                .ok_or_else(|| {
+                    Error::new(token.span.0, ErrorKind::Internal, "Unrecorded quantifier")
+                })?;
+            let (min, max) = quantifier_bounds(self.raw(token.span), info);
+            Some(Quantifier {
+                span: token.span,
+                min,
+                max,
+                greedy: info.greedy,
+            })
+        } else {
+            None
+        };
+        Ok(Term {
+            span: (start, self.cursor),
+            kind,
+            quantifier,
+        })
+    }
+
+    fn char(&self, span: Span) -> Char {
+        Char {
+            span,
+            value: self.raw(span).chars().next().unwrap_or_default(),
+        }
+    }
+
+    fn group(&mut self, open: Span) -> Result<TermKind, Error> {
+        let raw = self.raw(open);
+        let kind = if raw == "(" {
+            self.group_count += 1;
+            Some(GroupKind::Capturing {
+                index: self.group_count,
+                name: None,
+            })
+        } else if raw == "(?:" {
+            Some(GroupKind::NonCapturing)
+        } else if raw.starts_with("(?") && raw.ends_with(':') {
+            let mut flags = raw[2..raw.len() - 1].splitn(2, '-');
+            Some(GroupKind::Modifiers {
+                add: flags.next().unwrap_or_default().to_string(),
+                remove: flags.next().unwrap_or_default().to_string(),
+            })
+        } else if raw.starts_with("(?<") && raw.ends_with('>') {
+            self.group_count += 1;
+            Some(GroupKind::Capturing {
+                index: self.group_count,
+                name: Some(raw[3..raw.len() - 1].to_string()),
+            })
+        } else {
+            None
+        };
+        let disjunction = self.disjunction()?;
+        self.next();
+        let span = (open.0, self.cursor);
+        if let Some(kind) = kind {
+            return Ok(TermKind::Atom(Atom::Group(Group {
+                span,
+                kind,
+                disjunction,
+            })));
+        }
+        let negated = raw.ends_with('!');
+        let kind = if raw.starts_with("(?<") {
+            AssertionKind::Lookbehind {
+                negated,
+                disjunction,
+            }
+        } else {
+            AssertionKind::Lookahead {
+                negated,
+                disjunction,
+            }
+        };
+        Ok(TermKind::Assertion(Assertion { span, kind }))
+    }
+
+    fn class(&mut self, open: Span) -> Result<CharacterClass, Error> {
+        let negated = self.raw(open) == "[^";
+        let mut operation = ClassOperation::Union;
+        let mut items = Vec::new();
+        loop {
+            // set operators aren't recorded as tokens, they
+            // are the only thing that can appear between items
+            let gap_start = self.cursor;
+            let token = match self.next() {
+                Some(token) => token,
+                None => break,
+            };
+            match &self.pattern[gap_start..token.span.0] {
+                "&&" => operation = ClassOperation::Intersection,
+                "--" => operation = ClassOperation::Subtraction,
+                _ => {}
+            }
+            let atom = match token.kind {
+                TokenKind::ClassClose => break,
+                TokenKind::ClassOpen => {
+                    items.push(ClassItem::Class(self.class(token.span)?));
+                    continue;
+                }
+                TokenKind::Escape if self.raw(token.span).starts_with("\\q{") => {
+                    items.push(self.strings(token.span));
+                    continue;
+                }
+                TokenKind::Literal => ClassAtom::Char(self.char(token.span)),
+                TokenKind::Escape => ClassAtom::Escape(self.escape(token.span)),
+                _ => {
+                    return Err(Error::new(
+                        token.span.0,
+                        ErrorKind::Internal,
+                        &format!("Unexpected {:?} token in a class", token.kind),
+                    ))
+                }
+            };
+            if let Some(Token {
+                kind: TokenKind::ClassRange,
+                ..
+            }) = self.peek()
+            {
+                self.next();
+                let end = self
+                    .next()
+                    .ok_or_else(|| self.unexpected("Expected the end of a class range"))?;
+                let end = if end.kind == TokenKind::Escape {
+                    ClassAtom::Escape(self.escape(end.span))
+                } else {
+                    ClassAtom::Char(self.char(end.span))
+                };
+                items.push(ClassItem::Range {
+                    span: (token.span.0, self.cursor),
+                    start: atom,
+                    end,
+                });
+            } else {
+                items.push(ClassItem::Atom(atom));
+            }
+        }
+        Ok(CharacterClass {
+            span: (open.0, self.cursor),
+            negated,
+            operation,
+            items,
+        })
+    }
+
+    /// Split the body of a `\q{...}` on any `|` that isn't escaped
+    fn strings(&self, span: Span) -> ClassItem {
+        let raw = self.raw(span);
+        let mut strings = vec![String::new()];
+        let mut chars = raw[3..raw.len() - 1].chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '|' => strings.push(String::new()),
+                '\\' => {
+                    let current = strings.last_mut().unwrap();
+                    current.push(ch);
+                    current.extend(chars.next());
+                }
+                _ => strings.last_mut().unwrap().push(ch),
+            }
+        }
+        ClassItem::Strings { span, strings }
+    }
+
+    fn escape_atom(&self, span: Span) -> Atom {
+        let raw = self.raw(span);
+        let back_reference = self
+            .escapes
+            .iter()
+            .any(|e| e.span == span && e.back_reference);
+        if back_reference {
+            if let Some(name) = raw.strip_prefix("\\k<") {
+                return Atom::BackReference(BackReference {
+                    span,
+                    kind: BackReferenceKind::Named(name.trim_end_matches('>').to_string()),
+                });
+            }
+            if let Ok(n) = raw[1..].parse() {
+                return Atom::BackReference(BackReference {
+                    span,
+                    kind: BackReferenceKind::Numeric(n),
+                });
+            }
+        }
+        Atom::Escape(self.escape(span))
+    }
+
+    fn escape_value(&self, span: Span) -> Option<u32> {
+        self.escapes
+            .iter()
+            .find(|e| e.span == span)
+            .and_then(|e| e.value)
+    }
+
+    fn escape(&self, span: Span) -> Escape {
+        let raw = self.raw(span);
+        let kind = if let Some(value) = self.escape_value(span) {
+            EscapeKind::Character(value)
+        } else if raw.starts_with("\\p{") || raw.starts_with("\\P{") {
+            let body = &raw[3..raw.len() - 1];
+            let (name, value) = if let Some(idx) = body.find('=') {
+                (Some(body[..idx].to_string()), body[idx + 1..].to_string())
+            } else {
+                (None, body.to_string())
+            };
+            EscapeKind::Property {
+                negated: raw.starts_with("\\P"),
+                name,
+                value,
+            }
+        } else {
+            EscapeKind::Class(raw[1..].chars().next().unwrap_or_default())
+        };
+        Escape {
+            span,
+            kind,
+            raw: raw.to_string(),
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        self.disjunction.fmt(f)
+    }
+}
+
+impl Display for Disjunction {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for (i, alt) in self.alternatives.iter().enumerate() {
+            if i > 0 {
+                write!(f, "|")?;
+            }
+            alt.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Alternative {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for term in &self.terms {
+            term.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self.kind {
+            TermKind::Assertion(assertion) => assertion.fmt(f)?,
+            TermKind::Atom(atom) => atom.fmt(f)?,
+        }
+        if let Some(quantifier) = &self.quantifier {
+            quantifier.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Assertion {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self.kind {
+            AssertionKind::Start => write!(f, "^"),
+            AssertionKind::End => write!(f, "$"),
+            AssertionKind::WordBoundary => write!(f, "\\b"),
+            AssertionKind::NotWordBoundary => write!(f, "\\B"),
+            AssertionKind::Lookahead {
+                negated,
+                disjunction,
+            } => write!(f, "(?{}{})", if *negated { '!' } else { '=' }, disjunction),
+            AssertionKind::Lookbehind {
+                negated,
+                disjunction,
+            } => write!(f, "(?<{}{})", if *negated { '!' } else { '=' }, disjunction),
+        }
+    }
+}
+
+/// The bounds of a quantifier, `QuantifierInfo` clamps braced
+/// bounds to `u32::MAX` so those are read from the digits
+fn quantifier_bounds(raw: &str, info: &QuantifierInfo) -> (u64, Option<u64>) {
+    let braced = match raw.strip_prefix('{') {
+        Some(braced) => braced.trim_end_matches('?').trim_end_matches('}'),
+        None => return (info.min.into(), info.max.map(u64::from)),
+    };
+    let decimal = |digits: &str| {
+        digits.chars().fold(0u64, |value, ch| {
+            value
+                .saturating_mul(10)
+                .saturating_add(ch.to_digit(10).unwrap_or(0).into())
+        })
+    };
+    let mut parts = braced.splitn(2, ',');
+    let min = decimal(parts.next().unwrap_or_default());
+    let max = match parts.next() {
+        None => Some(min),
+        Some("") => None,
+        Some(digits) => Some(decimal(digits)),
+    };
+    (min, max)
+}
+
+impl Display for Quantifier {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match (self.min, self.max) {
+            (0, None) => write!(f, "*")?,
+            (1, None) => write!(f, "+")?,
+            (0, Some(1)) => write!(f, "?")?,
+            (min, None) => write!(f, "{{{},}}", min)?,
+            (min, Some(max)) if min == max => write!(f, "{{{}}}", min)?,
+            (min, Some(max)) => write!(f, "{{{},{}}}", min, max)?,
+        }
+        if !self.greedy {
+            write!(f, "?")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Atom {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Atom::Char(ch) => {
+                if crate::RegexParser::is_syntax_ch(ch.value) || ch.value == '/' {
+                    write!(f, "\\")?;
+                }
+                write!(f, "{}", ch.value)
+            }
+            Atom::Dot(_) => write!(f, "."),
+            Atom::Escape(escape) => write!(f, "{}", escape.raw),
+            Atom::BackReference(back_ref) => back_ref.fmt(f),
+            Atom::Class(class) => class.fmt(f),
+            Atom::Group(group) => group.fmt(f),
+        }
+    }
+}
+
+impl Display for BackReference {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self.kind {
+            BackReferenceKind::Numeric(n) => write!(f, "\\{}", n),
+            BackReferenceKind::Named(name) => write!(f, "\\k<{}>", name),
+        }
+    }
+}
+
+impl Display for CharacterClass {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "[")?;
+        if self.negated {
+            write!(f, "^")?;
+        }
+        let operator = match self.operation {
+            ClassOperation::Union => "",
+            ClassOperation::Intersection => "&&",
+            ClassOperation::Subtraction => "--",
+        };
+        for (i, item) in self.items.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", operator)?;
+            }
+            match item {
+                ClassItem::Atom(atom) => atom.fmt(f)?,
+                ClassItem::Range { start, end, .. } => write!(f, "{}-{}", start, end)?,
+                ClassItem::Class(class) => class.fmt(f)?,
+                ClassItem::Strings { strings, .. } => write!(f, "\\q{{{}}}", strings.join("|"))?,
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+impl Display for ClassAtom {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            ClassAtom::Char(ch) => {
+                if matches!(ch.value, ']' | '\\' | '^' | '-') {
+                    write!(f, "\\")?;
+                }
+                write!(f, "{}", ch.value)
+            }
+            ClassAtom::Escape(escape) => write!(f, "{}", escape.raw),
+        }
+    }
+}
+
+impl Display for Group {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self.kind {
+            GroupKind::Capturing { name: None, .. } => write!(f, "(")?,
+            GroupKind::Capturing {
+                name: Some(name), ..
+            } => write!(f, "(?<{}>", name)?,
+            GroupKind::NonCapturing => write!(f, "(?:")?,
+            GroupKind::Modifiers { add, remove } if remove.is_empty() => write!(f, "(?{}:", add)?,
+            GroupKind::Modifiers { add, remove } => write!(f, "(?{}-{}:", add, remove)?,
+        }
+        write!(f, "{})", self.disjunction)
+    }
+}
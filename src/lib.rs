@@ -1,155 +1,1978 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::{
+    borrow::Cow,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::cmp::Ordering;
 use log::trace;
-use std::{iter::Peekable, str::Chars};
 
+pub mod ast;
+mod matcher;
+pub mod prelude;
 mod unicode;
 mod unicode_tables;
 
+pub use matcher::is_match;
+pub use unicode::{canonicalize_property, UnicodeVersion, SUPPORTED_UNICODE_VERSION};
+
+/// An error encountered while validating a regular
+/// expression.
+///
+/// This struct is `#[non_exhaustive]`, more fields may be
+/// added in the future so any destructuring must include
+/// a `..` rest pattern (and any match on a future error
+/// kind enum must include a wildcard arm).
+///
+/// ```
+/// use res_regex::{Error, RegexParser};
+/// let err = RegexParser::new("/a/gg").err().unwrap();
+/// match err {
+///     Error { idx: 0, .. } => unreachable!(),
+///     Error { msg, idx, .. } => {
+///         assert_eq!(idx, 4);
+///         assert!(msg.contains("duplicate"));
+///     }
+/// }
+/// ```
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct Error {
+    /// What went wrong, for matching on specific failures
+    ///
+    /// ```
+    /// use res_regex::{ErrorKind, RegexParser};
+    /// let err = RegexParser::new("/a)/").unwrap().validate().unwrap_err();
+    /// assert_eq!(err.kind, ErrorKind::UnmatchedParen);
+    /// ```
+    pub kind: ErrorKind,
+    /// A description of the error for display
     pub msg: String,
+    /// The position of the error, `space` says what
+    /// this is a position in
     pub idx: usize,
+    pub space: IndexSpace,
+    /// The 1 based line of `idx` in the text it is a position
+    /// in, only a pattern embedded from multi-line source will
+    /// have anything other than 1
+    ///
+    /// ```
+    /// use res_regex::RegexParser;
+    /// let mut parser = RegexParser::from_parts("a\nb)", "").unwrap();
+    /// let err = parser.validate().unwrap_err();
+    /// assert_eq!((err.line, err.column), (2, 3));
+    /// assert_eq!(err.to_string(), "Unmatched `)` at 2:3");
+    /// ```
+    pub line: usize,
+    /// The 1 based column of `idx` on its line, in characters
+    pub column: usize,
+    /// `true` when the pattern failed to validate with the
+    /// `u` (or `v`) flag but would be valid without it
+    ///
+    /// ```
+    /// use res_regex::RegexParser;
+    /// let err = RegexParser::new(r"/\1/u").unwrap().validate().unwrap_err();
+    /// assert!(err.suggest_without_unicode);
+    /// ```
+    pub suggest_without_unicode: bool,
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{} at {}", self.msg, self.idx)
+/// The kind of an `Error`, more kinds may be added in the
+/// future so any match must include a wildcard arm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A literal without the `/` delimiters
+    InvalidLiteral,
+    /// A character that isn't one of the `VALID_FLAGS`
+    InvalidFlag,
+    /// The same flag more than once
+    DuplicateFlag,
+    /// Both the `u` and `v` flags
+    IncompatibleFlags,
+    /// Longer than `ParserOptions::max_pattern_len`
+    PatternTooLong,
+    /// More work than `ParserOptions::max_operations`
+    BudgetExceeded,
+    /// A `)` without a matching `(`
+    UnmatchedParen,
+    /// A `(` without a matching `)`
+    UnterminatedGroup,
+    /// A `[` without a matching `]`
+    UnterminatedClass,
+    /// Input left over after the pattern or class
+    TrailingCharacters,
+    /// A quantifier with nothing before it (`/*a/`)
+    NothingToRepeat,
+    /// A `{` or `}` that isn't part of a quantifier
+    LoneQuantifierBrackets,
+    /// A malformed braced quantifier (like `{2,1}`)
+    InvalidQuantifier,
+    /// An unknown or malformed escape sequence
+    InvalidEscape,
+    /// Anything after `(?` that isn't a known group prefix
+    InvalidGroup,
+    /// A missing or malformed name in `(?<name>`
+    InvalidGroupName,
+    /// A group name that was already defined
+    DuplicateGroupName,
+    /// A malformed `\k<name>` or one with no matching group
+    InvalidNamedReference,
+    /// A malformed character class
+    InvalidCharacterClass,
+    /// A class range where the start is after the end
+    RangeOutOfOrder,
+    /// An unknown name or value in `\p{...}`
+    InvalidUnicodeProperty,
+    /// A back reference to a group that doesn't exist
+    InvalidBackReference,
+    /// A valid pattern that `is_match` can't run
+    Unsupported,
+    /// A feature that is newer than `ParserOptions::ecma_version`
+    NewerThanTarget,
+    /// Groups or classes nested deeper than `ParserOptions::max_depth`
+    TooDeeplyNested,
+    /// The tokens recorded while validating couldn't be built
+    /// into a tree by `RegexParser::parse`, this is a bug in
+    /// the parser rather than a problem with the pattern
+    Internal,
+}
+
+/// The text that an `Error`'s `idx` is a position in
+///
+/// ```
+/// use res_regex::{IndexSpace, RegexParser};
+/// let err = RegexParser::new("/a/gg").err().unwrap();
+/// assert_eq!(err.space, IndexSpace::Literal);
+/// assert_eq!(err.literal_idx(), 4);
+/// let err = RegexParser::new("/a)/").unwrap().validate().unwrap_err();
+/// assert_eq!(err.space, IndexSpace::Pattern);
+/// assert_eq!(err.literal_idx(), err.idx + 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexSpace {
+    /// The whole literal (`/pattern/flags`), used for errors
+    /// from `RegexParser::new` and `RegexParser::with_options`
+    Literal,
+    /// The pattern between the `/`s, used for errors from
+    /// `validate` and anything built on it
+    Pattern,
+    /// A string of flags on its own, used by the functions
+    /// that take the flags separately (`flags_equivalent`,
+    /// `RegexParser::from_regex_source`)
+    Flags,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{} at {}:{}", self.msg, self.line, self.column)
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
 impl Error {
-    fn new(idx: usize, msg: &str) -> Self {
+    fn new(idx: usize, kind: ErrorKind, msg: &str) -> Self {
         Self {
+            kind,
             idx,
             msg: msg.to_string(),
+            space: IndexSpace::Pattern,
+            line: 1,
+            column: idx + 1,
+            suggest_without_unicode: false,
+        }
+    }
+
+    fn in_space(mut self, space: IndexSpace) -> Self {
+        self.space = space;
+        self
+    }
+    /// Compute `line` and `column` from `text`, the
+    /// text that `idx` is a position in
+    fn locate(mut self, text: &str) -> Self {
+        let mut end = self.idx.min(text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        let before = &text[..end];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        self.line = before.matches('\n').count() + 1;
+        self.column = before[line_start..].chars().count() + self.idx - end + 1;
+        self
+    }
+    /// `idx` as a position in a literal that started with `/`,
+    /// this is only meaningful for `Literal` and `Pattern` errors
+    /// from a parser constructed with `new` or `with_options`
+    pub fn literal_idx(&self) -> usize {
+        match self.space {
+            IndexSpace::Pattern => self.idx + 1,
+            _ => self.idx,
+        }
+    }
+}
+
+/// An escape sequence found while validating a pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapeInfo {
+    /// The span of the escape, including the `\`
+    pub span: (usize, usize),
+    /// The code point this escape represents, `None` for
+    /// escapes that aren't a single character like `\d`,
+    /// `\p{L}` or back references
+    pub value: Option<u32>,
+    /// `true` if this is a back reference (`\1` or `\k<name>`),
+    /// without the `u` flag a `\N` with no matching group is a
+    /// legacy octal escape instead and this will be `false`
+    pub back_reference: bool,
+}
+
+/// How a `\` followed by a digit was interpreted, without
+/// the `u` flag a number that isn't a back reference falls
+/// back to a legacy octal escape and then an identity escape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecimalResolution {
+    /// `\0` not followed by a digit, the NUL character
+    Null,
+    /// A reference to a capturing group
+    BackReference,
+    /// A legacy octal escape like `\1` or `\012`
+    LegacyOctal,
+    /// `\8` or `\9` matching the digit itself
+    Identity,
+}
+
+/// A `\` followed by a digit found while validating a
+/// pattern, see `RegexParser::decimal_escapes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecimalEscape {
+    /// The span of the escape, including the `\`
+    pub span: (usize, usize),
+    pub resolution: DecimalResolution,
+}
+
+/// A capturing group found while validating a pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupInfo<'a> {
+    /// The 1 based number used by back references
+    pub index: u32,
+    /// The name of a `(?<name>...)` group
+    pub name: Option<&'a str>,
+    /// The span from the `(` to the matching `)`
+    pub span: (usize, usize),
+}
+
+/// A back reference found while validating a pattern, see
+/// `RegexParser::back_references`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackRef<'a> {
+    /// A numbered reference like `\1`
+    Numeric {
+        index: u32,
+        /// The span of the reference, including the `\`
+        span: (usize, usize),
+    },
+    /// A named reference like `\k<name>`
+    Named {
+        name: &'a str,
+        /// The span of the reference, including the `\`
+        span: (usize, usize),
+    },
+}
+
+impl<'a> BackRef<'a> {
+    /// The span of the reference, including the `\`
+    pub fn span(&self) -> (usize, usize) {
+        match self {
+            Self::Numeric { span, .. } | Self::Named { span, .. } => *span,
+        }
+    }
+}
+
+/// The different kinds of tokens that make up a pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A character that matches itself, this includes
+    /// the members of a character class
+    Literal,
+    /// The `.` wildcard
+    Dot,
+    /// Any escape sequence except `\b` and `\B`
+    Escape,
+    /// `^`, `$`, `\b` or `\B`
+    Assertion,
+    /// The opening of any group, including the `?:`,
+    /// `?<name>` or look around prefix
+    GroupOpen,
+    /// The `)` closing a group
+    GroupClose,
+    /// The `|` separating alternatives
+    Alternation,
+    /// `*`, `+`, `?` or a braced quantifier including
+    /// the trailing `?` when lazy
+    Quantifier,
+    /// The `[` or `[^` opening a character class
+    ClassOpen,
+    /// The `]` closing a character class
+    ClassClose,
+    /// The `-` between two members of a character class
+    ClassRange,
+}
+
+/// A single token in a pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: (usize, usize),
+}
+
+/// A construct that is valid but likely a mistake,
+/// these are only collected when enabled via
+/// `ParserOptions::collect_warnings`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub msg: String,
+    pub idx: usize,
+    /// The span of the construct when it is more than a
+    /// single position (like the range in `[A-z]`), this
+    /// always starts at `idx`
+    pub span: Option<(usize, usize)>,
+}
+
+impl core::fmt::Display for Warning {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{} at {}", self.msg, self.idx)
+    }
+}
+
+/// An edition of the ECMAScript specification, see
+/// `ParserOptions::ecma_version`. Only the editions that
+/// changed regular expressions are listed, when targeting
+/// any other use the latest edition before it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum EcmaVersion {
+    Es5,
+    /// The `u` and `y` flags
+    Es2015,
+    /// The `s` flag, named groups, look behind assertions
+    /// and property escapes
+    Es2018,
+    /// The `d` flag
+    Es2022,
+    /// The `v` flag
+    Es2024,
+    /// Modifier groups (like `(?i:a)`)
+    #[default]
+    Es2025,
+}
+
+impl EcmaVersion {
+    /// The edition that introduced a flag
+    fn of_flag(flag: char) -> Self {
+        match flag {
+            'u' | 'y' => Self::Es2015,
+            's' => Self::Es2018,
+            'd' => Self::Es2022,
+            'v' => Self::Es2024,
+            _ => Self::Es5,
         }
     }
 }
 
+impl core::fmt::Display for EcmaVersion {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let name = match self {
+            Self::Es5 => "ES5",
+            Self::Es2015 => "ES2015",
+            Self::Es2018 => "ES2018",
+            Self::Es2022 => "ES2022",
+            Self::Es2024 => "ES2024",
+            Self::Es2025 => "ES2025",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Options for controlling how a pattern is validated
+#[derive(Debug, Clone, Default)]
+pub struct ParserOptions {
+    /// When `true` the Annex B (web compatibility) extensions
+    /// are disabled, even without the `u` flag. This includes
+    /// legacy octal escapes, extended atoms (like a lone `]`),
+    /// quantified lookaheads, out of range back references
+    /// being treated as escapes and identity escapes of
+    /// letters (like `\a`), both inside and outside of
+    /// character classes. `\k<name>` is always a named
+    /// reference, see `RegexParser::with_strict`
+    pub strict: bool,
+    /// Reject capturing groups without a name, `(?<name>...)`
+    /// and non-capturing `(?:...)` groups are still allowed
+    pub require_named_groups: bool,
+    /// The longest pattern (in bytes) that will be accepted,
+    /// anything longer is rejected before parsing begins.
+    /// `None` means there is no limit
+    pub max_pattern_len: Option<usize>,
+    /// The maximum number of primitive operations (consuming,
+    /// peeking or rewinding the input) allowed before validation
+    /// is aborted. `None` means there is no limit
+    pub max_operations: Option<u64>,
+    /// Validate unicode property values against the tables
+    /// for this version instead of `SUPPORTED_UNICODE_VERSION`
+    pub unicode_version: Option<UnicodeVersion>,
+    /// Collect warnings for suspicious but legal
+    /// constructs, see `RegexParser::warnings`
+    pub collect_warnings: bool,
+    /// Group names defined outside of the pattern (e.g. in a
+    /// later fragment), `\k<name>` may refer to any of these
+    /// and named back references are enabled from the start
+    pub declared_names: Vec<String>,
+    /// Add a warning for each legacy octal escape (like `\101`),
+    /// this doesn't require `collect_warnings`
+    pub warn_legacy_octal: bool,
+    /// Add a warning for each unbounded quantifier (`*`, `+` or
+    /// `{n,}`) inside of a look behind assertion, some engines
+    /// only support look behinds with a bounded length. This
+    /// doesn't require `collect_warnings`
+    pub warn_unbounded_lookbehind: bool,
+    /// Report every problem with the flags of a literal in
+    /// one error (like "duplicate flags: g, i") instead of
+    /// stopping at the first one. The error's `idx` and `kind`
+    /// are those of the first problem
+    pub collect_flag_errors: bool,
+    /// Reject flags and syntax that were added after this
+    /// edition, defaults to the latest
+    pub ecma_version: EcmaVersion,
+    /// The deepest that groups and classes (with the `v` flag)
+    /// can be nested, parsing them recurses so this prevents a
+    /// stack overflow. `None` means `DEFAULT_MAX_DEPTH`
+    pub max_depth: Option<usize>,
+}
+
+/// The default for `ParserOptions::max_depth`, this is deep
+/// enough for any hand written pattern while leaving plenty
+/// of room on a 2MB thread stack (even in a debug build)
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Builds a `RegexParser` one option at a time, anything not
+/// set keeps its value from `ParserOptions::default`
+///
+/// ```
+/// # use res_regex::{EcmaVersion, RegexParserBuilder};
+/// let builder = RegexParserBuilder::new()
+///     .strict(true)
+///     .version(EcmaVersion::Es2018)
+///     .collect_warnings(true);
+/// let mut parser = builder.build("/(?<a>b)|/").unwrap();
+/// assert_eq!(parser.validate_detailed().unwrap().len(), 1);
+/// assert!(builder.build("/a/v").is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RegexParserBuilder {
+    options: ParserOptions,
+}
+
+impl RegexParserBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Start from an existing set of options
+    pub fn from_options(options: ParserOptions) -> Self {
+        Self { options }
+    }
+    /// See `ParserOptions::strict`
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+    /// See `ParserOptions::ecma_version`
+    pub fn version(mut self, version: EcmaVersion) -> Self {
+        self.options.ecma_version = version;
+        self
+    }
+    /// See `ParserOptions::max_depth`
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = Some(max_depth);
+        self
+    }
+    /// See `ParserOptions::collect_warnings`
+    pub fn collect_warnings(mut self, collect_warnings: bool) -> Self {
+        self.options.collect_warnings = collect_warnings;
+        self
+    }
+    /// The options a parser will be built with
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+    /// Construct a parser for the regular expression literal
+    /// `js` with these options, the builder can be reused
+    pub fn build<'a>(&self, js: &'a str) -> Result<RegexParser<'a>, Error> {
+        RegexParser::with_options(js, self.options.clone())
+    }
+}
+
+/// A validator for a single regular expression
+///
+/// Cloning a parser captures its current position and state,
+/// so a caller can try one way of continuing on the clone
+/// and fall back to the original if that attempt fails
+#[derive(Clone)]
 pub struct RegexParser<'a> {
-    pattern: &'a str,
-    chars: Peekable<Chars<'a>>,
-    state: State<'a>,
+    pattern: Cow<'a, str>,
+    /// The character at `state.pos`
+    peeked: Option<char>,
+    state: State,
+    body_offset: usize,
+    /// `true` when the last call to `validate` succeeded
+    validated: bool,
+    flags: RegExFlags,
+}
+
+/// An owned summary of a validated pattern that doesn't borrow
+/// from the parser or the pattern, see `RegexParser::report`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RegexReport {
+    /// The number of capturing groups
+    pub capture_count: u32,
+    /// The names of the named groups, in the order they appear
+    pub group_names: Vec<String>,
+    pub flags: RegExFlags,
+    /// `true` if there is a look ahead or look behind assertion
+    pub uses_lookaround: bool,
+    /// The largest numbered back reference, 0 if there are none
+    pub max_back_reference: u32,
+    /// The fewest characters a match can consume, `None` if the
+    /// pattern can never match (like `/a[]/`). A class with `v`
+    /// flag strings (`\q{...}`) is counted as possibly empty
+    pub min_len: Option<usize>,
+    /// Any warnings that were collected
+    pub warnings: Vec<Warning>,
+}
+
+/// An iterator over the tokens of a pattern, see
+/// `RegexParser::tokens`
+pub struct TokenIter<'a> {
+    parser: Option<RegexParser<'a>>,
+    tokens: alloc::vec::IntoIter<Token>,
+    error: Option<Error>,
+}
+
+impl<'a> Iterator for TokenIter<'a> {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(mut parser) = self.parser.take() {
+            let result = parser.validate();
+            let mut tokens = core::mem::take(&mut parser.state.tokens);
+            if let Err(e) = result {
+                tokens.retain(|t| t.span.1 <= e.idx);
+                self.error = Some(e);
+            }
+            self.tokens = tokens.into_iter();
+        }
+        self.tokens
+            .next()
+            .map(Ok)
+            .or_else(|| self.error.take().map(Err))
+    }
+}
+
+impl<'a> core::iter::FusedIterator for TokenIter<'a> {}
+
+/// The kind of a quantifier without an upper bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnboundedKind {
+    /// `*`
+    Star,
+    /// `+`
+    Plus,
+    /// `{n,}`
+    AtLeast(u32),
+}
+
+/// A quantifier found while validating a pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuantifierInfo {
+    /// The position of the quantifier in the pattern
+    pub at: usize,
+    /// The minimum number of repetitions, larger
+    /// bounds are clamped to `u32::MAX`
+    pub min: u32,
+    /// The maximum number of repetitions, `None` when unbounded
+    pub max: Option<u32>,
+    /// `false` when followed by a `?` (lazy)
+    pub greedy: bool,
+    /// The span of the item being quantified
+    pub target_span: (usize, usize),
+    /// The span of the quantifier from `at`, including the
+    /// braces of `{n,m}` and a trailing `?` when lazy
+    pub span: (usize, usize),
 }
 
 impl<'a> RegexParser<'a> {
+    /// Construct a parser for a regular expression literal
+    /// (`/pattern/flags`). Any error returned here points into
+    /// `js` itself, so the whole literal (`0..js.len()`) is the
+    /// context to show alongside it
     pub fn new(js: &'a str) -> Result<Self, Error> {
+        Self::with_options(js, ParserOptions::default())
+    }
+
+    pub fn with_options(js: &'a str, options: ParserOptions) -> Result<Self, Error> {
+        let (pattern, flags) = Self::parse_literal(js, &options)?;
+        let mut ret = Self::from_pattern(pattern, &flags, options)?;
+        ret.body_offset = 1;
+        Ok(ret)
+    }
+    /// Construct a parser with the Annex B (web compatibility)
+    /// extensions disabled when `strict` is `true`, validating
+    /// the way an engine without them would. See
+    /// `ParserOptions::strict` for what is rejected
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::with_strict(r"/\1(a)\k<b>/", true).unwrap();
+    /// parser.validate().unwrap_err();
+    /// let mut parser = RegexParser::with_strict(r"/\1(a)\k<b>/", false).unwrap();
+    /// parser.validate().unwrap();
+    /// let mut parser = RegexParser::with_strict(r"/[\a\01]/", true).unwrap();
+    /// parser.validate().unwrap_err();
+    /// ```
+    pub fn with_strict(js: &'a str, strict: bool) -> Result<Self, Error> {
+        Self::with_options(
+            js,
+            ParserOptions {
+                strict,
+                ..ParserOptions::default()
+            },
+        )
+    }
+    /// Construct a parser that rejects anything added to
+    /// regular expressions after `version`
+    ///
+    /// ```
+    /// # use res_regex::{EcmaVersion, RegexParser};
+    /// let mut parser = RegexParser::with_version("/(?<a>b)/", EcmaVersion::Es2015).unwrap();
+    /// assert!(parser.validate().is_err());
+    /// assert!(RegexParser::with_version("/a/s", EcmaVersion::Es2015).is_err());
+    /// ```
+    pub fn with_version(js: &'a str, version: EcmaVersion) -> Result<Self, Error> {
+        Self::with_options(
+            js,
+            ParserOptions {
+                ecma_version: version,
+                ..ParserOptions::default()
+            },
+        )
+    }
+    /// Construct a parser that allows groups and classes to be
+    /// nested at most `max_depth` deep, see `ParserOptions::max_depth`
+    ///
+    /// ```
+    /// # use res_regex::{ErrorKind, RegexParser};
+    /// let mut parser = RegexParser::with_max_depth("/((a))/", 1).unwrap();
+    /// let err = parser.validate().unwrap_err();
+    /// assert_eq!((err.kind, err.idx), (ErrorKind::TooDeeplyNested, 2));
+    /// let mut parser = RegexParser::with_max_depth("/((a))/", 2).unwrap();
+    /// parser.validate().unwrap();
+    /// ```
+    pub fn with_max_depth(js: &'a str, max_depth: usize) -> Result<Self, Error> {
+        Self::with_options(
+            js,
+            ParserOptions {
+                max_depth: Some(max_depth),
+                ..ParserOptions::default()
+            },
+        )
+    }
+    /// Construct a parser from a pattern and flags that have
+    /// already been separated, the pattern should not include
+    /// the `/` delimiters. Errors from `validate` are positions
+    /// in `pattern` and errors for the flags are positions in
+    /// `flags` (`IndexSpace::Flags`)
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::from_parts("a+b", "gi").unwrap();
+    /// parser.validate().unwrap();
+    /// let err = RegexParser::from_parts("a+b", "gig").err().unwrap();
+    /// assert_eq!(err.idx, 2);
+    /// ```
+    pub fn from_parts(pattern: &'a str, flags: &'a str) -> Result<Self, Error> {
+        let options = ParserOptions::default();
+        let flags = RegExFlags::parse_with(flags, &options)?;
+        Self::from_pattern(pattern, &flags, options)
+    }
+    /// Construct a parser that owns its pattern, otherwise the
+    /// same as `from_parts`. The parser doesn't borrow from
+    /// anything so it can be kept after the strings it was
+    /// constructed from are gone (or sent to another thread)
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// fn parser_for(source: &str) -> RegexParser<'static> {
+    ///     RegexParser::new_owned(source.replace('_', "-"), String::from("u")).unwrap()
+    /// }
+    /// let mut parser = parser_for("(?<a>[a_z])");
+    /// parser.validate().unwrap();
+    /// assert_eq!(parser.group_names(), vec!["a"]);
+    /// ```
+    pub fn new_owned(pattern: String, flags: String) -> Result<Self, Error> {
+        let options = ParserOptions::default();
+        let flags = RegExFlags::parse_with(&flags, &options)?;
+        Self::from_pattern(pattern, &flags, options)
+    }
+    /// Reuse this parser for another pattern and flags (like
+    /// `from_parts`), keeping the options it was constructed with
+    /// and the capacity of its internal buffers. When validating
+    /// many patterns this avoids allocating a parser for each one.
+    /// If `flags` or `pattern` are rejected the parser is unchanged
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::from_parts("(?<a>b)", "").unwrap();
+    /// parser.validate().unwrap();
+    /// parser.reset_with(r"\u{61}", "u").unwrap();
+    /// parser.validate().unwrap();
+    /// assert!(parser.group_names().is_empty());
+    /// ```
+    pub fn reset_with(&mut self, pattern: &'a str, flags: &'a str) -> Result<(), Error> {
+        let options = ParserOptions {
+            ecma_version: self.state.ecma_version,
+            collect_flag_errors: self.state.collect_flag_errors,
+            ..ParserOptions::default()
+        };
+        let flags = RegExFlags::parse_with(flags, &options)?;
+        Self::check_pattern_len(pattern, self.state.max_pattern_len)?;
+        self.pattern = Cow::Borrowed(pattern);
+        self.peeked = pattern.chars().next();
+        self.body_offset = 0;
+        self.validated = false;
+        self.flags = flags;
+        self.state.reset();
+        self.state.len = pattern.len();
+        self.state.last_assert_is_quant = false;
+        self.state.last_quantifier_bounds = (0, None);
+        self.state.operations = 0;
+        self.state.u = flags.unicode || flags.unicode_sets;
+        self.state.v = flags.unicode_sets;
+        self.state.n = self.state.named_from_start();
+        self.state.case_insensitive = flags.case_insensitive;
+        Ok(())
+    }
+    /// Split a literal into its pattern and flags, errors
+    /// are positions in the literal
+    fn parse_literal(js: &'a str, options: &ParserOptions) -> Result<(&'a str, RegExFlags), Error> {
+        Self::split_literal(js, options).map_err(|e| e.in_space(IndexSpace::Literal).locate(js))
+    }
+
+    fn split_literal(js: &'a str, options: &ParserOptions) -> Result<(&'a str, RegExFlags), Error> {
         if !js.starts_with('/') {
             return Err(Error::new(
                 0,
+                ErrorKind::InvalidLiteral,
                 "regular expression literals must start with a /",
             ));
         }
-        let pat_end_idx = if let Some(end_idx) = js.rfind('/') {
-            if end_idx == 0 {
-                return Err(Error::new(0, "regular expression literals must have 2 `/`"));
-            } else {
-                end_idx
-            }
+        let pat_end_idx = if let Some(end_idx) = Self::closing_slash(js) {
+            end_idx
         } else {
-            return Err(Error::new(0, "regular expression literals must have 2 `/`"));
+            return Err(Error::new(
+                0,
+                ErrorKind::InvalidLiteral,
+                "regular expression literals must have 2 `/`",
+            ));
         };
         let pattern = if let Some(pattern) = js.get(1..pat_end_idx) {
             pattern
         } else {
-            return Err(Error::new(0, "Invalid regular expression"));
+            return Err(Error::new(
+                0,
+                ErrorKind::InvalidLiteral,
+                "Invalid regular expression",
+            ));
         };
         let flags = if let Some(flag_str) = js.get(pat_end_idx + 1..) {
-            let mut flags = RegExFlags::default();
-            for (i, c) in flag_str.chars().enumerate() {
-                flags.add_flag(c, pat_end_idx + i + 1)?;
-            }
-            flags
+            RegExFlags::parse_at(flag_str, pat_end_idx + 1, options)?
         } else {
-            return Err(Error::new(pat_end_idx, "invalid flags"));
+            return Err(Error::new(
+                pat_end_idx,
+                ErrorKind::InvalidLiteral,
+                "invalid flags",
+            ));
         };
+        Ok((pattern, flags))
+    }
+    /// The position of the `/` that ends the pattern of a literal,
+    /// this is the first one after the opening `/` that isn't
+    /// escaped or in a character class (like the `/` in `/[/]/`)
+    fn closing_slash(js: &str) -> Option<usize> {
+        let mut in_class = false;
+        let mut chars = js.char_indices().skip(1);
+        while let Some((idx, ch)) = chars.next() {
+            match ch {
+                '\\' => {
+                    chars.next();
+                }
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '/' if !in_class => return Some(idx),
+                _ => {}
+            }
+        }
+        None
+    }
+    /// Construct a parser the way `new RegExp(re.source, flags)`
+    /// would, `source` is the pattern without any delimiters so a
+    /// `/` is just another character (`.source` will have escaped it
+    /// as `\/`, which is also accepted). Error indexes for `flags`
+    /// are relative to the start of `flags`
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::from_regex_source(r"a\/b", "gu").unwrap();
+    /// parser.validate().unwrap();
+    /// ```
+    pub fn from_regex_source(source: &'a str, flags: &str) -> Result<Self, Error> {
+        let options = ParserOptions::default();
+        let flags = RegExFlags::parse_with(flags, &options)?;
+        Self::from_pattern(source, &flags, options)
+    }
+
+    /// Reject a pattern longer than `max` bytes, the error points
+    /// at the last character boundary that fits within the limit
+    fn check_pattern_len(pattern: &str, max: Option<usize>) -> Result<(), Error> {
+        if let Some(max) = max {
+            if pattern.len() > max {
+                let mut idx = max;
+                while !pattern.is_char_boundary(idx) {
+                    idx -= 1;
+                }
+                return Err(Error::new(
+                    idx,
+                    ErrorKind::PatternTooLong,
+                    &format!(
+                        "Pattern too long ({} bytes, the limit is {})",
+                        pattern.len(),
+                        max
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn from_pattern(
+        pattern: impl Into<Cow<'a, str>>,
+        flags: &RegExFlags,
+        options: ParserOptions,
+    ) -> Result<Self, Error> {
+        let pattern = pattern.into();
+        Self::check_pattern_len(&pattern, options.max_pattern_len)?;
+        let mut state = State::new(pattern.len(), flags.unicode || flags.unicode_sets, &options);
+        state.case_insensitive = flags.case_insensitive;
+        state.v = flags.unicode_sets;
         Ok(Self {
+            peeked: pattern.chars().next(),
             pattern,
-            chars: pattern.chars().peekable(),
-            state: State::new(pattern.len(), flags.unicode),
+            state,
+            body_offset: 0,
+            validated: false,
+            flags: *flags,
         })
     }
+    /// The position of the pattern in the text the parser was
+    /// constructed from, 1 for `new` and `with_options` (the
+    /// opening `/`) and 0 otherwise. Adding this to a `Pattern`
+    /// error's `idx` gives a position in the original text
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/a)/").unwrap();
+    /// let err = parser.validate().unwrap_err();
+    /// assert_eq!(err.idx + parser.body_offset(), 3);
+    /// ```
+    pub fn body_offset(&self) -> usize {
+        self.body_offset
+    }
 
+    /// Validate the pattern, this is `parse` without keeping
+    /// the tree. Any error returned points into the pattern
+    /// between the `/`s and not the whole literal
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new(r"/a\u{}/u").unwrap();
+    /// // the `{` is at 3 in the pattern, 4 in the literal
+    /// assert_eq!(parser.validate().unwrap_err().idx, 3);
+    /// ```
     pub fn validate(&mut self) -> Result<(), Error> {
+        self.parse().map(|_| ())
+    }
+    /// Walk the pattern, recording the tokens and other
+    /// information the `ast::Builder` and the other queries use
+    fn check(&mut self) -> Result<(), Error> {
         trace!("parse {:?}", self.current());
+        self.validated = false;
+        if let Err(e) = self.validate_passes() {
+            let mut e = e.locate(&self.pattern);
+            e.suggest_without_unicode = self.state.u && self.valid_without_unicode();
+            return Err(e);
+        }
+        if self.state.collect_warnings {
+            self.warn_sibling_back_refs();
+        }
+        self.validated = true;
+        Ok(())
+    }
+    /// Parse the pattern, a named group found without the `u` flag
+    /// means a second pass is needed but only if the first pass
+    /// parsed something that depends on named mode
+    fn validate_passes(&mut self) -> Result<(), Error> {
         self.pattern()?;
         if !self.state.n && !self.state.group_names.is_empty() {
-            self.pattern()?;
+            self.state.n = true;
+            if self.state.n_dependent {
+                self.pattern()?;
+            }
         }
         Ok(())
     }
-    /// The primary entry point, `Pattern` is technically
-    /// the target for all the characters inbetween the `/`s
-    /// ```js
-    /// let re = /pattern/
-    /// ```
-    fn pattern(&mut self) -> Result<(), Error> {
-        trace!("pattern {:?}", self.current(),);
-        if self.state.pos > 0 {
-            self.chars = self.pattern.chars().peekable();
-            self.state.reset();
-        }
-        self.disjunction()?;
-        if self.state.pos != self.state.len {
-            if self.eat(')') {
-                return Err(Error::new(self.state.pos, "Unmatched `)`"));
+    /// Re-parse the pattern without the `u` or `v` flag, this
+    /// is only done after validation has failed
+    fn valid_without_unicode(&self) -> bool {
+        let options = ParserOptions {
+            strict: self.state.strict,
+            max_operations: self.state.max_operations,
+            unicode_version: self.state.unicode_version,
+            declared_names: self.state.declared_names.clone(),
+            max_depth: Some(self.state.max_depth),
+            ..ParserOptions::default()
+        };
+        let flags = RegExFlags {
+            case_insensitive: self.state.case_insensitive,
+            ..RegExFlags::default()
+        };
+        RegexParser::from_pattern(&*self.pattern, &flags, options)
+            .and_then(|mut parser| parser.check())
+            .is_ok()
+    }
+    /// Warn about back references to a group in a different
+    /// alternative of the same disjunction (like `/(a)|\1/`),
+    /// the group can never have participated so they always
+    /// match the empty string
+    fn warn_sibling_back_refs(&mut self) {
+        // the (disjunction, alternative) pairs leading to a position
+        let mut path = vec![(0, 0)];
+        let mut disjunctions = 0;
+        let mut groups = Vec::new();
+        let mut refs = Vec::new();
+        for token in &self.state.tokens {
+            let raw = &self.pattern[token.span.0..token.span.1];
+            match token.kind {
+                TokenKind::GroupOpen => {
+                    if raw == "(" || (raw.starts_with("(?<") && raw.ends_with('>')) {
+                        let name = raw.get(3..raw.len() - 1).unwrap_or("");
+                        groups.push((name, path.clone()));
+                    }
+                    disjunctions += 1;
+                    path.push((disjunctions, 0));
+                }
+                TokenKind::GroupClose => {
+                    path.pop();
+                }
+                TokenKind::Alternation => {
+                    if let Some(last) = path.last_mut() {
+                        last.1 += 1;
+                    }
+                }
+                TokenKind::Escape => {
+                    let is_ref = self
+                        .state
+                        .escapes
+                        .iter()
+                        .any(|e| e.span == token.span && e.value.is_none());
+                    if is_ref {
+                        refs.push((token.span.0, raw, path.clone()));
+                    }
+                }
+                _ => {}
             }
-            if self.eat(']') || self.eat('}') {
-                return Err(Error::new(self.state.pos, "Lone quantifier brackets"));
+        }
+        let mut warnings = Vec::new();
+        for (idx, raw, ref_path) in refs {
+            let group = if let Some(name) = raw.strip_prefix("\\k<") {
+                let name = name.trim_end_matches('>');
+                groups.iter().find(|(n, _)| *n == name)
+            } else if let Ok(n) = raw[1..].parse::<usize>() {
+                groups.get(n.wrapping_sub(1))
+            } else {
+                None
+            };
+            if let Some((_, group_path)) = group {
+                let sibling = group_path
+                    .iter()
+                    .zip(ref_path.iter())
+                    .take_while(|(g, r)| g.0 == r.0)
+                    .any(|(g, r)| g.1 != r.1);
+                if sibling {
+                    warnings.push(idx);
+                }
             }
         }
-        if self.state.max_back_refs > self.state.num_capturing_parens {
-            return Err(Error::new(self.state.pos, "Invalid escape"));
+        for idx in warnings {
+            self.warn(
+                idx,
+                "back reference to a group in a different alternative always matches the empty string",
+            );
         }
-        for name in &self.state.back_ref_names {
-            if !self.state.group_names.contains(name) {
-                return Err(Error::new(
-                    self.state.pos,
-                    "Invalid named capture referenced",
-                ));
+    }
+    /// Validate `edited`, a copy of the pattern that has been
+    /// changed starting at `edit_start`. When the edit is contained
+    /// in a non-capturing group or character class only that
+    /// region is parsed again and the rest of the last call to
+    /// `validate` is reused, otherwise (or if the last call to
+    /// `validate` failed) the whole pattern is validated again.
+    /// Regions containing capturing groups, look arounds or back
+    /// references are always fully validated
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/a(?:bc)+d/").unwrap();
+    /// parser.validate().unwrap();
+    /// parser.revalidate_region("a(?:b|c)+d", 5).unwrap();
+    /// assert!(parser.revalidate_region("a(?:b|c{)+d", 7).is_ok());
+    /// assert!(parser.revalidate_region("a(?:b|c{2,1})+d", 7).is_err());
+    /// ```
+    pub fn revalidate_region(&mut self, edited: &'a str, edit_start: usize) -> Result<(), Error> {
+        if self.validated {
+            if let Some(result) = self.try_revalidate_region(edited, edit_start) {
+                return result;
             }
         }
-        Ok(())
+        trace!("revalidate_region falling back to a full parse");
+        self.pattern = Cow::Borrowed(edited);
+        self.peeked = edited.chars().next();
+        self.state.reset();
+        self.state.len = edited.len();
+        self.state.n = self.state.named_from_start();
+        self.validate()
     }
-    /// A disjunction will be items separated by a `|`
-    /// ```js
-    /// let re = /dis|junction/
-    /// ```
-    fn disjunction(&mut self) -> Result<(), Error> {
-        trace!("disjunction {:?}", self.current(),);
-        self.alternative()?;
-        while self.eat('|') {
-            self.alternative()?;
+    /// Attempt to parse only the region of `edited` around `edit_start`,
+    /// `None` means the whole pattern needs to be validated
+    fn try_revalidate_region(
+        &mut self,
+        edited: &'a str,
+        edit_start: usize,
+    ) -> Option<Result<(), Error>> {
+        // a class that may contain strings changes what is allowed
+        // in the classes around it, so `v` patterns are always re-parsed
+        if self.state.v {
+            return None;
         }
-        if self.eat_quantifier(true)? {
-            return Err(Error::new(self.state.pos, "Nothing to repeat"));
+        // the region may be inside of a look behind
+        if self.state.warn_unbounded_lookbehind && self.state.uses_lookaround {
+            return None;
         }
-        if self.eat('{') {
-            return Err(Error::new(self.state.pos, "lone quantifier brackets"));
+        let (start, old_end) = self.region_around(edit_start)?;
+        let old = &self.pattern;
+        let new_end = (old_end + edited.len()).checked_sub(old.len())?;
+        if new_end <= start
+            || edited.get(..start)? != &old[..start]
+            || edited.get(new_end..)? != &old[old_end..]
+        {
+            return None;
         }
-        Ok(())
+        let region = edited.get(start..new_end)?;
+        let bytes = region.as_bytes();
+        if bytes
+            .windows(2)
+            .any(|pair| pair[0] == b'\\' && (pair[1] == b'k' || (b'1'..=b'9').contains(&pair[1])))
+        {
+            return None;
+        }
+        // the groups and classes around the region count towards `max_depth`
+        let depth = self
+            .state
+            .tokens
+            .iter()
+            .take_while(|token| token.span.0 < start)
+            .fold(0, |depth, token| match token.kind {
+                TokenKind::GroupOpen | TokenKind::ClassOpen => depth + 1,
+                TokenKind::GroupClose | TokenKind::ClassClose => depth - 1,
+                _ => depth,
+            });
+        let saved = (
+            core::mem::take(&mut self.state.tokens),
+            core::mem::take(&mut self.state.escapes),
+            core::mem::take(&mut self.state.quantifiers),
+            core::mem::take(&mut self.state.classes),
+            core::mem::take(&mut self.state.warnings),
+            core::mem::take(&mut self.state.group_names),
+            core::mem::take(&mut self.state.back_ref_names),
+            core::mem::take(&mut self.state.back_refs),
+            core::mem::take(&mut self.state.groups),
+            self.state.num_capturing_parens,
+            self.state.uses_lookaround,
+        );
+        self.state.num_capturing_parens = 0;
+        self.state.uses_lookaround = false;
+        self.pattern = Cow::Borrowed(edited);
+        self.state.len = edited.len();
+        self.reset_to(start);
+        self.state.depth = depth;
+        let parsed = if edited[start..].starts_with('[') {
+            self.eat_character_class()
+        } else {
+            self.eat_uncapturing_group()
+        };
+        self.state.depth = 0;
+        let clean = self.state.num_capturing_parens == 0
+            && self.state.group_names.is_empty()
+            && self.state.back_refs.is_empty()
+            && !self.state.uses_lookaround;
+        if !matches!(parsed, Ok(true)) || self.state.pos != new_end || !clean {
+            return None;
+        }
+        let (
+            tokens,
+            escapes,
+            quantifiers,
+            classes,
+            warnings,
+            group_names,
+            back_ref_names,
+            back_refs,
+            groups,
+            count,
+            lookaround,
+        ) = saved;
+        let delta = new_end as isize - old_end as isize;
+        let shift = |idx: usize| {
+            if idx >= old_end {
+                (idx as isize + delta) as usize
+            } else {
+                idx
+            }
+        };
+        let outside = |span: (usize, usize)| span.1 <= start || span.0 >= old_end;
+        let shift_span = |span: (usize, usize)| (shift(span.0), shift(span.1));
+        macro_rules! merge {
+            ($field:ident, $old:expr, $span:expr, $shift:expr) => {{
+                let mut merged: Vec<_> = $old
+                    .into_iter()
+                    .filter(|item| outside($span(item)))
+                    .map($shift)
+                    .collect();
+                merged.append(&mut self.state.$field);
+                merged.sort_by_key(|item| $span(item).0);
+                self.state.$field = merged;
+            }};
+        }
+        merge!(tokens, tokens, |t: &Token| t.span, |mut t: Token| {
+            t.span = shift_span(t.span);
+            t
+        });
+        merge!(
+            escapes,
+            escapes,
+            |e: &EscapeInfo| e.span,
+            |mut e: EscapeInfo| {
+                e.span = shift_span(e.span);
+                e
+            }
+        );
+        merge!(
+            quantifiers,
+            quantifiers,
+            |q: &QuantifierInfo| (q.at, q.at),
+            |mut q: QuantifierInfo| {
+                q.at = shift(q.at);
+                q.target_span = shift_span(q.target_span);
+                q.span = shift_span(q.span);
+                q
+            }
+        );
+        merge!(
+            classes,
+            classes,
+            |c: &ClassInfo| c.span,
+            |mut c: ClassInfo| {
+                c.span = shift_span(c.span);
+                c
+            }
+        );
+        merge!(
+            warnings,
+            warnings,
+            |w: &Warning| (w.idx, w.idx),
+            |mut w: Warning| {
+                w.idx = shift(w.idx);
+                w.span = w.span.map(shift_span);
+                w
+            }
+        );
+        self.state.group_names = group_names
+            .into_iter()
+            .map(|(name, idx)| (shift_span(name), shift(idx)))
+            .collect();
+        self.state.back_ref_names = back_ref_names
+            .into_iter()
+            .map(|(name, idx)| (shift_span(name), shift(idx)))
+            .collect();
+        self.state.back_refs = back_refs
+            .into_iter()
+            .map(|back_ref| match back_ref {
+                BackRefSpan::Numeric { index, span } => BackRefSpan::Numeric {
+                    index,
+                    span: shift_span(span),
+                },
+                BackRefSpan::Named { name, span } => BackRefSpan::Named {
+                    name: shift_span(name),
+                    span: shift_span(span),
+                },
+            })
+            .collect();
+        self.state.groups = groups
+            .into_iter()
+            .map(|mut group| {
+                group.span = shift_span(group.span);
+                group.name = group.name.map(shift_span);
+                group
+            })
+            .collect();
+        self.state.num_capturing_parens = count;
+        self.state.uses_lookaround = lookaround;
+        self.reset_to(edited.len());
+        Some(Ok(()))
     }
-    /// An alternative is either side of a `disjunction`
-    /// ```js
-    /// let re = /alt1|alt2/;
-    /// ```
-    fn alternative(&mut self) -> Result<(), Error> {
-        trace!("alternative {:?}", self.current(),);
-        while self.state.pos < self.state.len && self.eat_term()? {}
-        Ok(())
+    /// The span of the innermost non-capturing group or class
+    /// that contains `idx`, `None` if `idx` isn't inside of one
+    /// or the innermost group is some other kind of group
+    fn region_around(&self, idx: usize) -> Option<(usize, usize)> {
+        let mut opens = Vec::new();
+        let mut best: Option<(usize, usize, bool)> = None;
+        for token in &self.state.tokens {
+            match token.kind {
+                TokenKind::GroupOpen | TokenKind::ClassOpen => opens.push(token.span),
+                TokenKind::GroupClose | TokenKind::ClassClose => {
+                    let open = opens.pop()?;
+                    let (start, end) = (open.0, token.span.1);
+                    if start < idx
+                        && idx < end
+                        && best.map(|(s, e, _)| e - s > end - start).unwrap_or(true)
+                    {
+                        let raw = &self.pattern[open.0..open.1];
+                        let eligible = raw == "(?:" || raw.starts_with('[');
+                        best = Some((start, end, eligible));
+                    }
+                }
+                _ => {}
+            }
+        }
+        match best {
+            Some((start, end, true)) => Some((start, end)),
+            _ => None,
+        }
     }
-    /// a quantifier is appended to an item to say how
-    /// many of that item should exist, this includes `*` (0 or more)
-    /// `+` (1 or more), `?` (0 or 1) or `{1}`, `{1,2}`
+    /// Validate the pattern collecting any warnings, even when
+    /// `collect_warnings` wasn't enabled
     ///
-    /// ```js
-    /// let re = /s*p+q?a{1}b{1,2}/;
     /// ```
-    fn eat_quantifier(&mut self, no_error: bool) -> Result<bool, Error> {
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/a[]/").unwrap();
+    /// let warnings = parser.validate_detailed().unwrap();
+    /// assert_eq!(warnings[0].idx, 1);
+    /// ```
+    pub fn validate_detailed(&mut self) -> Result<Vec<Warning>, Error> {
+        let collect_warnings = self.state.collect_warnings;
+        self.state.collect_warnings = true;
+        let result = self.validate();
+        self.state.collect_warnings = collect_warnings;
+        result.map(|_| self.state.warnings.clone())
+    }
+    /// Validate the pattern and build a tree representation
+    /// of it, see the `ast` module
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/a|(b)+/").unwrap();
+    /// let pattern = parser.parse().unwrap();
+    /// assert_eq!(pattern.disjunction.alternatives.len(), 2);
+    /// assert_eq!(pattern.to_string(), "a|(b)+");
+    /// ```
+    pub fn parse(&mut self) -> Result<ast::Pattern, Error> {
+        self.check()?;
+        ast::Builder::new(
+            &self.pattern,
+            &self.state.tokens,
+            &self.state.quantifiers,
+            &self.state.escapes,
+        )
+        .build()
+    }
+    /// Validate the pattern and return the position of every
+    /// syntax character that is acting as an operator, escaped
+    /// characters, group names and literal characters inside
+    /// of a class are not included
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new(r"/a\.[.]+/").unwrap();
+    /// assert_eq!(
+    ///     parser.operator_positions().unwrap(),
+    ///     vec![(3, '['), (5, ']'), (6, '+')]
+    /// );
+    /// ```
+    pub fn operator_positions(&mut self) -> Result<Vec<(usize, char)>, Error> {
+        self.validate()?;
+        let mut ret = Vec::new();
+        for token in &self.state.tokens {
+            if let TokenKind::Literal | TokenKind::Escape = token.kind {
+                continue;
+            }
+            let mut text = &self.pattern[token.span.0..token.span.1];
+            // the name in `(?<name>` is not an operator
+            if token.kind == TokenKind::GroupOpen
+                && text.starts_with("(?<")
+                && !text.starts_with("(?<=")
+                && !text.starts_with("(?<!")
+            {
+                text = "(?<";
+            }
+            for (i, ch) in text.char_indices() {
+                if Self::is_syntax_ch(ch) && ch != '\\' {
+                    ret.push((token.span.0 + i, ch));
+                }
+            }
+        }
+        Ok(ret)
+    }
+    /// `true` when the `u` (or `v`) flag was set
+    pub fn is_unicode_mode(&self) -> bool {
+        self.state.u
+    }
+    /// `true` when `\k<name>` was treated as a named back
+    /// reference during the last call to `validate`, this is
+    /// always the case with the `u` flag and otherwise only
+    /// when the pattern contains a named group
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/(?<a>x)/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert!(parser.is_named_mode());
+    /// assert!(!parser.is_unicode_mode());
+    /// ```
+    pub fn is_named_mode(&self) -> bool {
+        self.state.n
+    }
+    /// Validate the pattern and explain it in plain English,
+    /// see `ast::Pattern::describe`
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/^(a|b)$/").unwrap();
+    /// assert_eq!(
+    ///     parser.describe().unwrap(),
+    ///     "/^(a|b)$/ means: the start of the input, then \
+    ///      group 1 (either ('a') or ('b')), then the end of the input"
+    /// );
+    /// ```
+    pub fn describe(&mut self) -> Result<String, Error> {
+        let pattern = self.parse()?;
+        Ok(format!("/{}/ means: {}", self.pattern, pattern.describe()))
+    }
+    /// An owned summary of the last call to `validate`, this
+    /// can be kept (or sent to another thread) after the
+    /// parser and pattern are gone
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new(r"/(a)|\d{2}/").unwrap();
+    /// parser.validate().unwrap();
+    /// let report = parser.report();
+    /// assert_eq!(report.capture_count, 1);
+    /// assert_eq!(report.min_len, Some(1));
+    /// ```
+    pub fn report(&self) -> RegexReport {
+        let min_len = if self.validated {
+            ast::Builder::new(
+                &self.pattern,
+                &self.state.tokens,
+                &self.state.quantifiers,
+                &self.state.escapes,
+            )
+            .build()
+            .ok()
+            .and_then(|pattern| pattern.min_len())
+        } else {
+            None
+        };
+        RegexReport {
+            capture_count: self.state.num_capturing_parens,
+            group_names: self.group_names().into_iter().map(String::from).collect(),
+            flags: self.flags,
+            uses_lookaround: self.state.uses_lookaround,
+            max_back_reference: self.state.max_back_refs,
+            min_len,
+            warnings: self.state.warnings.clone(),
+        }
+    }
+    /// The flags the parser was constructed with
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let parser = RegexParser::new("/abc/gi").unwrap();
+    /// let flags = parser.flags();
+    /// assert!(flags.global() && flags.case_insensitive());
+    /// assert!(!flags.sticky());
+    /// ```
+    pub fn flags(&self) -> &RegExFlags {
+        &self.flags
+    }
+    /// The number of capturing groups found during
+    /// the last call to `validate`
+    pub fn capture_count(&self) -> u32 {
+        self.state.num_capturing_parens
+    }
+    /// `true` if the last call to `validate` found at least
+    /// one capturing group
+    pub fn has_capturing_groups(&self) -> bool {
+        self.state.num_capturing_parens > 0
+    }
+    /// `true` if the last call to `validate` found a back
+    /// reference (`\1` or `\k<name>`), without one the capture
+    /// positions only matter if the caller wants them
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/(a)/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert!(parser.has_capturing_groups());
+    /// assert!(!parser.has_referenced_captures());
+    /// ```
+    pub fn has_referenced_captures(&self) -> bool {
+        self.state.escapes.iter().any(|e| e.back_reference)
+    }
+    /// The kind of the first token in the pattern, this
+    /// will be `None` for an empty pattern or if `validate`
+    /// has not been called
+    ///
+    /// ```
+    /// # use res_regex::{RegexParser, TokenKind};
+    /// let mut parser = RegexParser::new("/^abc+/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert_eq!(parser.first_token(), Some(TokenKind::Assertion));
+    /// assert_eq!(parser.last_token(), Some(TokenKind::Quantifier));
+    /// ```
+    pub fn first_token(&self) -> Option<TokenKind> {
+        self.state.tokens.first().map(|t| t.kind)
+    }
+    /// The kind of the last token in the pattern, this
+    /// will be `None` for an empty pattern or if `validate`
+    /// has not been called
+    pub fn last_token(&self) -> Option<TokenKind> {
+        self.state.tokens.last().map(|t| t.kind)
+    }
+    /// Convert this parser into an iterator over the tokens
+    /// of its pattern. The pattern is validated when the first
+    /// token is requested, if it is invalid the tokens before
+    /// the error are produced followed by the error itself
+    ///
+    /// ```
+    /// # use res_regex::{RegexParser, TokenKind};
+    /// let parser = RegexParser::new("/(a)+/").unwrap();
+    /// let kinds: Vec<TokenKind> = parser.tokens().map(|t| t.unwrap().kind).collect();
+    /// assert_eq!(
+    ///     kinds,
+    ///     vec![
+    ///         TokenKind::GroupOpen,
+    ///         TokenKind::Literal,
+    ///         TokenKind::GroupClose,
+    ///         TokenKind::Quantifier,
+    ///     ]
+    /// );
+    /// ```
+    pub fn tokens(self) -> TokenIter<'a> {
+        TokenIter {
+            parser: Some(self),
+            tokens: Vec::new().into_iter(),
+            error: None,
+        }
+    }
+    /// All of the quantifiers found during the last
+    /// call to `validate`, in the order they appear
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/a+b{2,3}?/").unwrap();
+    /// parser.validate().unwrap();
+    /// let quants = parser.quantifiers();
+    /// assert_eq!(quants[1].min, 2);
+    /// assert_eq!(quants[1].max, Some(3));
+    /// assert!(!quants[1].greedy);
+    /// ```
+    pub fn quantifiers(&self) -> Vec<QuantifierInfo> {
+        self.state.quantifiers.clone()
+    }
+    /// The position and kind of every quantifier without an
+    /// upper bound found during the last call to `validate`
+    ///
+    /// ```
+    /// # use res_regex::{RegexParser, UnboundedKind};
+    /// let mut parser = RegexParser::new("/a*b?/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert_eq!(parser.unbounded_quantifiers(), vec![(1, UnboundedKind::Star)]);
+    /// ```
+    pub fn unbounded_quantifiers(&self) -> Vec<(usize, UnboundedKind)> {
+        self.state
+            .quantifiers
+            .iter()
+            .filter(|q| q.max.is_none())
+            .map(|q| {
+                let kind = match self.pattern[q.at..].chars().next() {
+                    Some('*') => UnboundedKind::Star,
+                    Some('+') => UnboundedKind::Plus,
+                    _ => UnboundedKind::AtLeast(q.min),
+                };
+                (q.at, kind)
+            })
+            .collect()
+    }
+    /// `true` if the last call to `validate` found a
+    /// look ahead or look behind assertion
+    pub fn uses_lookaround(&self) -> bool {
+        self.state.uses_lookaround
+    }
+    /// The largest numbered back reference (`\1`) found
+    /// during the last call to `validate`, 0 if none were found
+    pub fn max_back_reference(&self) -> u32 {
+        self.state.max_back_refs
+    }
+    /// All of the escapes (`\n`, `\x41`, `\d`, etc) found during
+    /// the last call to `validate`, in the order they appear
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new(r"/\t[\x41]/").unwrap();
+    /// parser.validate().unwrap();
+    /// let escapes = parser.escapes();
+    /// assert_eq!(escapes[0].value, Some(0x09));
+    /// assert_eq!(escapes[1].span, (3, 7));
+    /// ```
+    pub fn escapes(&self) -> &[EscapeInfo] {
+        &self.state.escapes
+    }
+    /// How each escape starting with a digit (`\0` to `\9`) found
+    /// during the last call to `validate` was interpreted, in the
+    /// order they appear. This includes escapes in character
+    /// classes, where a digit is never a back reference
+    ///
+    /// ```
+    /// # use res_regex::{DecimalResolution, RegexParser};
+    /// let mut parser = RegexParser::new(r"/(a)\1\2\8/").unwrap();
+    /// parser.validate().unwrap();
+    /// let found: Vec<_> = parser
+    ///     .decimal_escapes()
+    ///     .iter()
+    ///     .map(|e| e.resolution)
+    ///     .collect();
+    /// assert_eq!(
+    ///     found,
+    ///     vec![
+    ///         DecimalResolution::BackReference,
+    ///         DecimalResolution::LegacyOctal,
+    ///         DecimalResolution::Identity,
+    ///     ]
+    /// );
+    /// ```
+    pub fn decimal_escapes(&self) -> Vec<DecimalEscape> {
+        self.state
+            .escapes
+            .iter()
+            .filter_map(|escape| {
+                let raw = &self.pattern[escape.span.0 + 1..escape.span.1];
+                let resolution = match raw.as_bytes().first()? {
+                    b'1'..=b'9' if escape.back_reference => DecimalResolution::BackReference,
+                    b'0' if raw.len() == 1 => DecimalResolution::Null,
+                    b'0'..=b'7' => DecimalResolution::LegacyOctal,
+                    b'8' | b'9' => DecimalResolution::Identity,
+                    _ => return None,
+                };
+                Some(DecimalEscape {
+                    span: escape.span,
+                    resolution,
+                })
+            })
+            .collect()
+    }
+    /// The warnings collected during the last call to `validate`,
+    /// this will always be empty unless `collect_warnings` (or
+    /// `warn_legacy_octal` or `warn_unbounded_lookbehind`) was enabled
+    ///
+    /// ```
+    /// # use res_regex::{RegexParser, ParserOptions};
+    /// let options = ParserOptions {
+    ///     collect_warnings: true,
+    ///     ..ParserOptions::default()
+    /// };
+    /// let mut parser = RegexParser::with_options("/[a.b]/", options).unwrap();
+    /// parser.validate().unwrap();
+    /// assert_eq!(parser.warnings()[0].idx, 2);
+    /// ```
+    pub fn warnings(&self) -> &[Warning] {
+        &self.state.warnings
+    }
+    /// The code point ranges (inclusive) that a character class
+    /// matches, `span` should be the span of the class including
+    /// the brackets. This will return `None` if there is no class
+    /// at that span, the class is negated or the class contains
+    /// something that can't be enumerated (like `\w` or `\p{L}`)
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/[a-c]/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert_eq!(parser.class_as_char_set((0, 5)), Some(vec![(0x61, 0x63)]));
+    /// ```
+    pub fn class_as_char_set(&self, span: (usize, usize)) -> Option<Vec<(u32, u32)>> {
+        self.state
+            .classes
+            .iter()
+            .find(|class| class.span == span)
+            .and_then(|class| class.ranges.clone())
+    }
+    /// The names of the named groups found during the last
+    /// call to `validate`, in the order they appear. A name
+    /// can only be defined once so there are no duplicates
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new(r"/(?<y>\d{4})-(?<m>\d{2})/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert_eq!(parser.group_names(), vec!["y", "m"]);
+    /// ```
+    pub fn group_names(&self) -> Vec<&str> {
+        self.state
+            .group_names
+            .iter()
+            .map(|(name, _)| self.text_at(*name))
+            .collect()
+    }
+    /// Every capturing group found during the last call to
+    /// `validate`, in the order they are numbered
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new(r"/(a(?<b>c))d/").unwrap();
+    /// parser.validate().unwrap();
+    /// let groups = parser.groups();
+    /// assert_eq!((groups[0].index, groups[0].span), (1, (0, 10)));
+    /// assert_eq!((groups[1].name, groups[1].span), (Some("b"), (2, 9)));
+    /// ```
+    pub fn groups(&self) -> Vec<GroupInfo<'_>> {
+        self.state
+            .groups
+            .iter()
+            .map(|group| GroupInfo {
+                index: group.index,
+                name: group.name.map(|name| self.text_at(name)),
+                span: group.span,
+            })
+            .collect()
+    }
+    /// The position of every `\k<name>` back reference to
+    /// `name` found during the last call to `validate`
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new(r"/(?<x>a)\k<x>b\k<x>/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert_eq!(parser.references_to("x"), vec![7, 13]);
+    /// ```
+    pub fn references_to(&self, name: &str) -> Vec<usize> {
+        self.state
+            .back_ref_names
+            .iter()
+            .filter(|(n, _)| self.text_at(*n) == name)
+            .map(|(_, idx)| *idx)
+            .collect()
+    }
+    /// Every back reference (`\1` or `\k<name>`) found during
+    /// the last call to `validate`, in the order they appear.
+    /// Without the `u` flag a `\N` with no matching group is a
+    /// legacy octal escape and isn't included
+    ///
+    /// ```
+    /// # use res_regex::{BackRef, RegexParser};
+    /// let mut parser = RegexParser::new(r"/(?<x>a)\1\k<x>/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert_eq!(
+    ///     parser.back_references(),
+    ///     vec![
+    ///         BackRef::Numeric { index: 1, span: (7, 9) },
+    ///         BackRef::Named { name: "x", span: (9, 14) },
+    ///     ]
+    /// );
+    /// ```
+    pub fn back_references(&self) -> Vec<BackRef<'_>> {
+        self.state
+            .back_refs
+            .iter()
+            .map(|back_ref| match *back_ref {
+                BackRefSpan::Numeric { index, span } => BackRef::Numeric { index, span },
+                BackRefSpan::Named { name, span } => BackRef::Named {
+                    name: self.text_at(name),
+                    span,
+                },
+            })
+            .collect()
+    }
+    /// The text of a span in the pattern
+    fn text_at(&self, span: (usize, usize)) -> &str {
+        &self.pattern[span.0..span.1]
+    }
+    /// The number of `|` separated alternatives outside of
+    /// any group found during the last call to `validate`
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/a|(b|c)|/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert_eq!(parser.top_level_branch_count(), 3);
+    /// ```
+    pub fn top_level_branch_count(&self) -> usize {
+        let mut depth = 0;
+        let mut count = 1;
+        for token in &self.state.tokens {
+            match token.kind {
+                TokenKind::GroupOpen => depth += 1,
+                TokenKind::GroupClose => depth -= 1,
+                TokenKind::Alternation if depth == 0 => count += 1,
+                _ => {}
+            }
+        }
+        count
+    }
+    /// The fixed strings a match must start with, one for each
+    /// top level alternative (after expanding a leading group),
+    /// found during the last call to `validate`. This is `None`
+    /// if any alternative could start with something other than
+    /// a literal or with the `i` flag, since the prefixes are
+    /// meant for an exact multi-string search
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new("/(foo|bar)x|baz/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert_eq!(
+    ///     parser.literal_prefixes(),
+    ///     Some(vec!["foo".to_string(), "bar".to_string(), "baz".to_string()])
+    /// );
+    /// ```
+    pub fn literal_prefixes(&self) -> Option<Vec<String>> {
+        if !self.validated || self.state.case_insensitive {
+            return None;
+        }
+        ast::Builder::new(
+            &self.pattern,
+            &self.state.tokens,
+            &self.state.quantifiers,
+            &self.state.escapes,
+        )
+        .build()
+        .ok()?
+        .literal_prefixes()
+    }
+    /// `true` if the pattern contains no back references (numbered
+    /// or named) and no look around assertions, the constructs
+    /// that require a backtracking engine. A pattern that is
+    /// linear safe could be handed off to an engine like RE2
+    ///
+    /// ```
+    /// # use res_regex::RegexParser;
+    /// let mut parser = RegexParser::new(r"/(a)\1/").unwrap();
+    /// parser.validate().unwrap();
+    /// assert!(!parser.is_linear_safe());
+    /// ```
+    pub fn is_linear_safe(&self) -> bool {
+        !self.uses_lookaround()
+            && self.max_back_reference() == 0
+            && self.state.back_ref_names.is_empty()
+    }
+    /// The primary entry point, `Pattern` is technically
+    /// the target for all the characters inbetween the `/`s
+    /// ```js
+    /// let re = /pattern/
+    /// ```
+    fn pattern(&mut self) -> Result<(), Error> {
+        trace!("pattern {:?}", self.current(),);
+        if self.state.pos > 0 {
+            self.peeked = self.pattern.chars().next();
+            self.state.reset();
+        }
+        self.disjunction()?;
+        if self.state.pos != self.state.len {
+            if self.eat(')') {
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::UnmatchedParen,
+                    "Unmatched `)`",
+                ));
+            }
+            if self.eat(']') || self.eat('}') {
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::LoneQuantifierBrackets,
+                    "Lone quantifier brackets",
+                ));
+            }
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::TrailingCharacters,
+                "Unexpected trailing characters",
+            ));
+        }
+        if self.state.max_back_refs > self.state.num_capturing_parens {
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::InvalidEscape,
+                "Invalid escape",
+            ));
+        }
+        for (name, _) in &self.state.back_ref_names {
+            let name = self.text_at(*name);
+            if !self
+                .state
+                .group_names
+                .iter()
+                .any(|(n, _)| self.text_at(*n) == name)
+                && !self.state.declared_names.iter().any(|n| n == name)
+            {
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::InvalidNamedReference,
+                    "Invalid named capture referenced",
+                ));
+            }
+        }
+        Ok(())
+    }
+    /// A disjunction will be items separated by a `|`
+    /// ```js
+    /// let re = /dis|junction/
+    /// ```
+    fn disjunction(&mut self) -> Result<(), Error> {
+        trace!("disjunction {:?}", self.current(),);
+        self.check_budget()?;
+        let mut start = self.state.pos;
+        self.alternative()?;
+        let mut empty = self.state.pos == start;
+        loop {
+            let bar = self.state.pos;
+            if !self.eat('|') {
+                break;
+            }
+            self.push_token(TokenKind::Alternation, bar);
+            if empty {
+                self.warn(start, "an empty alternative matches the empty string");
+            }
+            start = self.state.pos;
+            self.alternative()?;
+            empty = self.state.pos == start;
+            if empty && self.current() != Some(&'|') {
+                self.warn(start, "an empty alternative matches the empty string");
+            }
+        }
+        if self.eat_quantifier((self.state.pos, self.state.pos), true)? {
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::NothingToRepeat,
+                "Nothing to repeat",
+            ));
+        }
+        if self.eat('{') {
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::LoneQuantifierBrackets,
+                "lone quantifier brackets",
+            ));
+        }
+        Ok(())
+    }
+    /// The `disjunction` inside of a group
+    fn nested_disjunction(&mut self) -> Result<(), Error> {
+        self.enter_nested()?;
+        self.disjunction()?;
+        self.state.depth -= 1;
+        Ok(())
+    }
+    /// Called when entering a group or character class, each of
+    /// these recurses so there is a limit to how deep they can go
+    /// before the stack would overflow. Every call should be paired
+    /// with decrementing `depth` when leaving
+    fn enter_nested(&mut self) -> Result<(), Error> {
+        self.state.depth += 1;
+        if self.state.depth > self.state.max_depth {
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::TooDeeplyNested,
+                "Expression too deeply nested",
+            ));
+        }
+        Ok(())
+    }
+    /// An alternative is either side of a `disjunction`
+    /// ```js
+    /// let re = /alt1|alt2/;
+    /// ```
+    fn alternative(&mut self) -> Result<(), Error> {
+        trace!("alternative {:?}", self.current(),);
+        while self.state.pos < self.state.len && self.eat_term()? {}
+        Ok(())
+    }
+    /// a quantifier is appended to an item to say how
+    /// many of that item should exist, this includes `*` (0 or more)
+    /// `+` (1 or more), `?` (0 or 1) or `{1}`, `{1,2}`
+    ///
+    /// ```js
+    /// let re = /s*p+q?a{1}b{1,2}/;
+    /// ```
+    ///
+    /// `target` is the span of the item being quantified
+    fn eat_quantifier(&mut self, target: (usize, usize), no_error: bool) -> Result<bool, Error> {
         trace!("eat_quantifier {:?}", self.current(),);
+        let at = self.state.pos;
         Ok(if self.eat_quantifier_prefix(no_error)? {
-            self.eat('?');
+            let greedy = !self.eat('?');
+            self.push_token(TokenKind::Quantifier, at);
+            let (min, max) = self.state.last_quantifier_bounds;
+            self.state.quantifiers.push(QuantifierInfo {
+                at,
+                min,
+                max,
+                greedy,
+                target_span: target,
+                span: (at, self.state.pos),
+            });
+            if max.is_none()
+                && self.state.lookbehind_depth > 0
+                && self.state.warn_unbounded_lookbehind
+            {
+                self.state.warnings.push(Warning {
+                    idx: at,
+                    span: None,
+                    msg: "Unbounded quantifier in a look behind assertion".to_string(),
+                });
+            }
             true
         } else {
             false
@@ -159,10 +1982,18 @@ impl<'a> RegexParser<'a> {
     /// the full braced quantifier `{1} or `{1,2}`
     fn eat_quantifier_prefix(&mut self, no_error: bool) -> Result<bool, Error> {
         trace!("eat_quantifier_prefix {:?}", self.current(),);
-        let ret = self.eat('*')
-            || self.eat('+')
-            || self.eat('?')
-            || self.eat_braced_quantifier(no_error)?;
+        let ret = if self.eat('*') {
+            self.state.last_quantifier_bounds = (0, None);
+            true
+        } else if self.eat('+') {
+            self.state.last_quantifier_bounds = (1, None);
+            true
+        } else if self.eat('?') {
+            self.state.last_quantifier_bounds = (0, Some(1));
+            true
+        } else {
+            self.eat_braced_quantifier(no_error)?
+        };
         Ok(ret)
     }
     /// A braced quantifier either 1 or two numbers wrapped in
@@ -181,27 +2012,46 @@ impl<'a> RegexParser<'a> {
         trace!("eat_braced_quantifier {:?}", self.current(),);
         let start = self.state.pos;
         if self.eat('{') {
+            let min_start = self.state.pos;
             if self.eat_digits(10) {
                 let min = self.state.last_int_value;
-                let max = if self.eat(',') && self.eat_digits(10) {
-                    self.state.last_int_value
+                let min_span = (min_start, self.state.pos);
+                let mut max_span = min_span;
+                let max = if self.eat(',') {
+                    let max_start = self.state.pos;
+                    if self.eat_digits(10) {
+                        max_span = (max_start, self.state.pos);
+                        self.state.last_int_value
+                    } else {
+                        None
+                    }
                 } else {
-                    None
+                    min
                 };
                 if self.eat('}') {
-                    if let (Some(max), Some(min)) = (max, min) {
-                        if max < min && !no_error {
-                            return Err(Error::new(
-                                self.state.pos,
-                                &format!("numbers out of order in {{{},{}}}", min, max),
-                            ));
-                        }
+                    // the bounds are clamped to `u32::MAX` so they
+                    // are compared using their digits
+                    let (min_text, max_text) = (self.text_at(min_span), self.text_at(max_span));
+                    if max.is_some()
+                        && Self::cmp_decimal(max_text, min_text) == Ordering::Less
+                        && !no_error
+                    {
+                        return Err(Error::new(
+                            self.state.pos,
+                            ErrorKind::InvalidQuantifier,
+                            &format!("numbers out of order in {{{},{}}}", min_text, max_text),
+                        ));
                     }
+                    self.state.last_quantifier_bounds = (min.unwrap_or(0), max);
                     return Ok(true);
                 }
             }
-            if self.state.u && !no_error {
-                return Err(Error::new(self.state.pos, "Incomplete quantifier"));
+            if (self.state.u || self.state.strict) && !no_error {
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::InvalidQuantifier,
+                    "Incomplete quantifier",
+                ));
             }
             self.reset_to(start);
         }
@@ -216,19 +2066,30 @@ impl<'a> RegexParser<'a> {
     /// ```
     fn eat_term(&mut self) -> Result<bool, Error> {
         trace!("eat_term {:?}", self.current(),);
+        self.check_budget()?;
+        let start = self.state.pos;
         if self.eat_assertion()? {
-            if self.state.last_assert_is_quant && self.eat_quantifier(false)? && self.state.n {
-                return Err(Error::new(self.state.pos, "Invalid quantifier"));
+            let target = (start, self.state.pos);
+            if self.state.last_assert_is_quant && self.eat_quantifier(target, false)? {
+                // Annex B only allows a quantified lookahead without
+                // the `u` flag, named groups don't change that
+                if self.state.u || self.state.strict {
+                    return Err(Error::new(
+                        self.state.pos,
+                        ErrorKind::InvalidQuantifier,
+                        "Invalid quantifier",
+                    ));
+                }
             }
             return Ok(true);
         }
-        if self.state.u {
+        if self.state.u || self.state.strict {
             if self.eat_atom()? {
-                self.eat_quantifier(false)?;
+                self.eat_quantifier((start, self.state.pos), false)?;
                 return Ok(true);
             }
         } else if self.eat_extended_atom()? {
-            self.eat_quantifier(false)?;
+            self.eat_quantifier((start, self.state.pos), false)?;
             return Ok(true);
         }
         Ok(false)
@@ -241,8 +2102,8 @@ impl<'a> RegexParser<'a> {
     /// ```
     fn eat_atom(&mut self) -> Result<bool, Error> {
         trace!("eat_atom {:?}", self.current(),);
-        let ret = self.eat_pattern_characters()
-            || self.eat('.')
+        let ret = self.eat_pattern_character()
+            || self.eat_dot()
             || self.eat_reverse_solidus_atom_escape()?
             || self.eat_character_class()?
             || self.eat_uncapturing_group()?
@@ -253,7 +2114,7 @@ impl<'a> RegexParser<'a> {
     /// exotic classes and groups
     fn eat_extended_atom(&mut self) -> Result<bool, Error> {
         trace!("eat_extended_atom {:?}", self.current(),);
-        let ret = self.eat('.')
+        let ret = self.eat_dot()
             || self.eat_reverse_solidus_atom_escape()?
             || self.eat_character_class()?
             || self.eat_uncapturing_group()?
@@ -262,12 +2123,25 @@ impl<'a> RegexParser<'a> {
             || self.eat_extended_pattern_character();
         Ok(ret)
     }
+    /// The `.` wildcard
+    fn eat_dot(&mut self) -> bool {
+        let start = self.state.pos;
+        if self.eat('.') {
+            self.push_token(TokenKind::Dot, start);
+            return true;
+        }
+        false
+    }
     /// attempts to consume a braced quantifier
     /// in an invalid position.
     fn eat_invalid_braced_quantifier(&mut self) -> Result<bool, Error> {
         trace!("eat_invalid_braced_quantifier {:?}", self.current(),);
         if self.eat_braced_quantifier(true)? {
-            return Err(Error::new(self.state.pos, "Nothing to repeat"));
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::NothingToRepeat,
+                "Nothing to repeat",
+            ));
         }
         Ok(false)
     }
@@ -275,7 +2149,7 @@ impl<'a> RegexParser<'a> {
     /// like `(` or `|`
     fn eat_extended_pattern_character(&mut self) -> bool {
         trace!("eat_extended_pattern_character {:?}", self.current(),);
-        if let Some(ch) = self.chars.peek() {
+        if let Some(ch) = self.peeked.as_ref() {
             if *ch != '$'
                 && !(*ch >= '(' && *ch <= '+')
                 && *ch != '.'
@@ -283,37 +2157,42 @@ impl<'a> RegexParser<'a> {
                 && *ch != '['
                 && *ch != '^'
                 && *ch != '|'
+                // a `\` is only a literal when it starts an invalid `\c` escape
+                && (*ch != '\\' || self.pattern[self.state.pos + 1..].starts_with('c'))
             {
+                let start = self.state.pos;
                 self.advance();
+                self.push_token(TokenKind::Literal, start);
                 return true;
             }
         }
         false
     }
     /// A pattern character is any non-syntax
-    /// character
-    fn eat_pattern_characters(&mut self) -> bool {
-        trace!("eat_pattern_characters {:?}", self.current(),);
-        let start = self.state.pos;
-        while let Some(next) = self.chars.peek() {
+    /// character, only one is consumed so that
+    /// a following quantifier applies to it alone
+    fn eat_pattern_character(&mut self) -> bool {
+        trace!("eat_pattern_character {:?}", self.current(),);
+        if let Some(next) = self.peeked.as_ref() {
             if !Self::is_syntax_ch(*next) {
+                let start = self.state.pos;
                 self.advance();
-            } else {
-                break;
+                self.push_token(TokenKind::Literal, start);
+                return true;
             }
         }
-        self.state.pos != start
+        false
     }
     /// Syntax characters are operators
     /// that have special meanin in a regular expression
     /// like `?` or `.`
     fn is_syntax_ch(ch: char) -> bool {
         ch == '$'
-            || ch >= '(' && ch <= '+'
+            || ('('..='+').contains(&ch)
             || ch == '.'
             || ch == '?'
-            || ch >= '[' && ch <= '^'
-            || ch >= '{' && ch <= '}'
+            || ('['..='^').contains(&ch)
+            || ('{'..='}').contains(&ch)
     }
 
     /// a reverse solidus is a really fancy name for `\`
@@ -322,32 +2201,46 @@ impl<'a> RegexParser<'a> {
         let start = self.state.pos;
         if self.eat('\\') {
             if self.eat_atom_escape()? {
+                self.push_escape(start);
                 return Ok(true);
             }
             self.reset_to(start);
         }
         Ok(false)
     }
-    /// Picking up after a `\`
+    /// Picking up after a `\`, when this returns `true`
+    /// `last_int_value` will be the decoded character or
+    /// `None` if the escape isn't a single character
     fn eat_atom_escape(&mut self) -> Result<bool, Error> {
         trace!("eat_atom_escape {}", self.state.u,);
         if self.eat_back_ref()
             || self.eat_character_class_escape()?
-            || self.eat_character_escape()?
             || self.state.n && self.eat_k_group_name()?
         {
+            self.state.last_int_value = None;
+            return Ok(true);
+        }
+        if self.eat_character_escape()? {
             return Ok(true);
         }
         trace!("previous check failed, {}", self.state.u);
-        if self.state.u {
+        if self.state.u || self.state.strict {
             trace!("previous all failed, with unicode flag");
             if let Some(next) = self.current() {
                 if *next == 'c' {
-                    return Err(Error::new(self.state.pos, "Invalid unicode escape"));
+                    return Err(Error::new(
+                        self.state.pos,
+                        ErrorKind::InvalidEscape,
+                        "Invalid unicode escape",
+                    ));
                 }
             }
             trace!("returning error");
-            return Err(Error::new(self.state.pos, "Invalid escape"));
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::InvalidEscape,
+                "Invalid escape",
+            ));
         }
         Ok(false)
     }
@@ -367,27 +2260,34 @@ impl<'a> RegexParser<'a> {
             } else {
                 return true;
             };
-            if self.state.u {
+            if self.state.u || self.state.strict || n <= self.state.num_capturing_parens {
                 if n > self.state.max_back_refs {
                     self.state.max_back_refs = n;
                 }
-                return true;
-            }
-            if n <= self.state.num_capturing_parens {
+                // include the `\` that came before the number
+                self.state.back_refs.push(BackRefSpan::Numeric {
+                    index: n,
+                    span: (start - 1, self.state.pos),
+                });
                 return true;
             }
             self.reset_to(start);
         }
         false
     }
-    /// an escaped decimal number
+    /// an escaped decimal number, this can't start with
+    /// a `0` since `\0` is the NUL character and not a
+    /// reference to a group 0
     fn eat_decimal_escape(&mut self) -> bool {
         trace!("eat_decimal_escape {:?}", self.current(),);
         let start = self.state.pos;
-        let mut last_int_value = 0;
-        while let Some(next) = self.chars.peek() {
+        if self.current() == Some(&'0') {
+            return false;
+        }
+        let mut last_int_value: u32 = 0;
+        while let Some(next) = self.peeked.as_ref() {
             if let Some(n) = next.to_digit(10) {
-                last_int_value = 10 * last_int_value + n;
+                last_int_value = last_int_value.saturating_mul(10).saturating_add(n);
                 self.advance()
             } else {
                 break;
@@ -402,19 +2302,38 @@ impl<'a> RegexParser<'a> {
     /// include `\p{General_Category=Greek}`
     fn eat_character_class_escape(&mut self) -> Result<bool, Error> {
         trace!("eat_character_class_escape {:?}", self.current(),);
-        if let Some(next) = self.chars.peek() {
+        if let Some(next) = self.peeked.as_ref() {
             if Self::is_character_class_escape(*next) {
                 self.state.last_int_value = None;
                 self.advance();
                 return Ok(true);
             }
             if self.state.u && (*next == 'P' || *next == 'p') {
+                let negated = *next == 'P';
+                self.require_version(self.state.pos - 1, EcmaVersion::Es2018, "Property escapes")?;
                 self.state.last_int_value = None;
                 self.advance();
+                let name_start = self.state.pos + 1;
                 if self.eat('{') && self.eat_unicode_property_value_expression()? && self.eat('}') {
+                    if negated
+                        && self.state.v
+                        && unicode::is_property_of_strings(
+                            &self.pattern[name_start..self.state.pos - 1],
+                        )
+                    {
+                        return Err(Error::new(
+                            name_start,
+                            ErrorKind::InvalidUnicodeProperty,
+                            "Properties of strings can't be negated",
+                        ));
+                    }
                     return Ok(true);
                 }
-                return Err(Error::new(self.state.pos, "Invalid property name"));
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::InvalidUnicodeProperty,
+                    "Invalid property name",
+                ));
             }
         }
         Ok(false)
@@ -427,16 +2346,21 @@ impl<'a> RegexParser<'a> {
         if self.eat_unicode_property_name() && self.eat('=') {
             let name = self.state.last_string_value;
             if self.eat_unicode_property_value() {
+                let name = name.map(|name| self.text_at(name));
                 self.validate_unicode_property_name_and_value(
-                    &name,
-                    &self.state.last_string_value,
+                    name,
+                    self.state.last_string(&self.pattern),
                 )?;
                 return Ok(true);
             }
         }
         self.reset_to(start);
         if self.eat_lone_unicode_property_name_or_value() {
-            self.validate_unicode_property_name_or_value(&self.state.last_string_value)?;
+            let name_or_value = self.state.last_string(&self.pattern);
+            if self.state.v && name_or_value.is_some_and(unicode::is_property_of_strings) {
+                return Ok(true);
+            }
+            self.validate_unicode_property_name_or_value(name_or_value)?;
             return Ok(true);
         }
         Ok(false)
@@ -452,7 +2376,7 @@ impl<'a> RegexParser<'a> {
         trace!("eat_unicode_property_name {:?}", self.current(),);
         let start = self.state.pos;
         self.state.last_string_value = None;
-        while let Some(ch) = self.chars.peek() {
+        while let Some(ch) = self.peeked.as_ref() {
             if Self::is_unicode_property_name_character(*ch) {
                 self.advance();
             } else {
@@ -460,7 +2384,7 @@ impl<'a> RegexParser<'a> {
             }
         }
         if self.state.pos != start {
-            self.state.last_string_value = self.pattern.get(start..self.state.pos)
+            self.state.last_string_value = Some((start, self.state.pos))
         }
         self.state.last_string_value.is_some()
     }
@@ -469,7 +2393,7 @@ impl<'a> RegexParser<'a> {
     fn eat_unicode_property_value(&mut self) -> bool {
         trace!("eat_unicode_property_value {:?}", self.current(),);
         let start = self.state.pos;
-        while let Some(next) = self.chars.peek() {
+        while let Some(next) = self.peeked.as_ref() {
             if Self::is_unicode_property_value_character(*next) {
                 self.advance();
             } else {
@@ -477,7 +2401,7 @@ impl<'a> RegexParser<'a> {
             }
         }
         if start != self.state.pos {
-            self.state.last_string_value = self.pattern.get(start..self.state.pos);
+            self.state.last_string_value = Some((start, self.state.pos));
         }
         self.state.last_string_value.is_some()
     }
@@ -494,51 +2418,60 @@ impl<'a> RegexParser<'a> {
     /// are valid
     fn validate_unicode_property_name_and_value(
         &self,
-        name: &Option<&'a str>,
-        value: &Option<&'a str>,
+        name: Option<&str>,
+        value: Option<&str>,
     ) -> Result<(), Error> {
         if let (Some(name), Some(value)) = (name, value) {
-            if !unicode::validate_name_and_value(name, value) {
-                Err(Error {
-                    idx: self.state.pos,
-                    msg: format!(
-                        "Unable to validate unicode property name and value ({:?} and {:?})",
-                        name, value
+            let valid = if let Some(version) = self.state.unicode_version {
+                unicode::validate_name_and_value_at(name, value, version)
+            } else {
+                unicode::validate_name_and_value(name, value)
+            };
+            if !valid {
+                Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::InvalidUnicodeProperty,
+                    &format!(
+                        "Unable to validate unicode property name and value ({:?} and {:?})",
+                        name, value
                     ),
-                })
+                ))
             } else {
                 Ok(())
             }
         } else {
-            Err(Error {
-                idx: self.state.pos,
-                msg: "Invalid unicode property name & value provided".to_string(),
-            })
+            Err(Error::new(
+                self.state.pos,
+                ErrorKind::InvalidUnicodeProperty,
+                "Invalid unicode property name & value provided",
+            ))
         }
     }
     /// Validates that a lone name or value
     /// is valid
     fn validate_unicode_property_name_or_value(
         &self,
-        name_or_value: &Option<&'a str>,
+        name_or_value: Option<&str>,
     ) -> Result<(), Error> {
         if let Some(name) = name_or_value {
             if !unicode::validate_name_or_value(name) {
-                Err(Error {
-                    idx: self.state.pos,
-                    msg: format!(
+                Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::InvalidUnicodeProperty,
+                    &format!(
                         "Unable to validate unicode property name or value ({:?})",
                         name_or_value
                     ),
-                })
+                ))
             } else {
                 Ok(())
             }
         } else {
-            Err(Error {
-                idx: self.state.pos,
-                msg: "Invalid unicoe property name or value".to_string(),
-            })
+            Err(Error::new(
+                self.state.pos,
+                ErrorKind::InvalidUnicodeProperty,
+                "Invalid unicode property name or value",
+            ))
         }
     }
     /// This will be any control letter plus `_`
@@ -547,11 +2480,11 @@ impl<'a> RegexParser<'a> {
     }
     /// This will be any name character plus and decimal digit
     fn is_unicode_property_value_character(ch: char) -> bool {
-        Self::is_unicode_property_name_character(ch) || ch.is_digit(10)
+        Self::is_unicode_property_name_character(ch) || ch.is_ascii_digit()
     }
     /// Any capital or lowercase english character
     fn is_control_letter(ch: char) -> bool {
-        (ch >= 'A' && ch <= 'Z') || (ch >= 'a' && ch <= 'z')
+        ch.is_ascii_alphabetic()
     }
     /// `d`, `D`, `s`, `S`, `w`, `W`
     fn is_character_class_escape(ch: char) -> bool {
@@ -565,13 +2498,13 @@ impl<'a> RegexParser<'a> {
             || self.eat_zero()
             || self.eat_hex_escape_sequence()?
             || self.eat_unicode_escape_sequence()?
-            || (!self.state.u && self.eat_legacy_octal_escape_sequence())
+            || (!self.state.u && !self.state.strict && self.eat_legacy_octal_escape_sequence())
             || self.eat_identity_escape();
         Ok(ret)
     }
     /// Peek at the current look ahead token
     fn current(&mut self) -> Option<&char> {
-        self.chars.peek()
+        self.peeked.as_ref()
     }
     /// control escapes include `\t`, `\n`, `\v`, `\f` and `\r`
     ///
@@ -580,7 +2513,7 @@ impl<'a> RegexParser<'a> {
     /// ```
     fn eat_control_escape(&mut self) -> bool {
         trace!("eat_control_escape {:?}", self.current(),);
-        if let Some(ch) = self.chars.peek() {
+        if let Some(ch) = self.peeked.as_ref() {
             match ch {
                 't' => self.state.last_int_value = Some(9),
                 'n' => self.state.last_int_value = Some(10),
@@ -618,7 +2551,7 @@ impl<'a> RegexParser<'a> {
     /// Eat a letter after a `\c`
     fn eat_control_letter(&mut self) -> bool {
         trace!("eat_control_letter {:?}", self.current(),);
-        if let Some(next) = self.chars.peek() {
+        if let Some(next) = self.peeked.as_ref() {
             if Self::is_control_letter(*next) {
                 let n: u32 = (*next).into();
                 self.state.last_int_value = Some(n % 0x20);
@@ -631,10 +2564,16 @@ impl<'a> RegexParser<'a> {
     /// Eat a zero character
     fn eat_zero(&mut self) -> bool {
         trace!("eat_zero {:?}", self.current(),);
-        if let Some(zero) = self.chars.peek() {
+        if let Some(zero) = self.peeked.as_ref() {
             if *zero == '0' {
                 self.state.last_int_value = Some(0);
                 self.advance();
+                // `\0` can't be followed by another digit, without
+                // `u` that is a legacy octal escape instead
+                if self.current().is_some_and(|ch| ch.is_ascii_digit()) {
+                    self.reset_to(self.state.pos - 1);
+                    return false;
+                }
                 return true;
             }
         }
@@ -649,7 +2588,11 @@ impl<'a> RegexParser<'a> {
                 return Ok(true);
             }
             if self.state.u {
-                return Err(Error::new(start, "Invalid escape"));
+                return Err(Error::new(
+                    start,
+                    ErrorKind::InvalidEscape,
+                    "Invalid escape",
+                ));
             }
             self.reset_to(start)
         }
@@ -675,6 +2618,7 @@ impl<'a> RegexParser<'a> {
     /// Eat a sequence of numbers starting with 0, all below 8
     fn eat_legacy_octal_escape_sequence(&mut self) -> bool {
         trace!("eat_legacy_octal_escape_sequence {:?}", self.current(),);
+        let start = self.state.pos;
         let last_int_value;
         if let Some(n1) = self.eat_digit(8) {
             if let Some(n2) = self.eat_digit(8) {
@@ -691,6 +2635,19 @@ impl<'a> RegexParser<'a> {
                 last_int_value = n1;
             }
             self.state.last_int_value = Some(last_int_value);
+            if self.state.warn_legacy_octal {
+                // include the `\` that came before the digits
+                self.state.warnings.push(Warning {
+                    idx: start - 1,
+                    span: None,
+                    msg: format!(
+                        "Legacy octal escape \\{}; use \\x{:02X} or \\u{{{:X}}}",
+                        &self.pattern[start..self.state.pos],
+                        last_int_value,
+                        last_int_value
+                    ),
+                });
+            }
             return true;
         }
         false
@@ -699,7 +2656,7 @@ impl<'a> RegexParser<'a> {
     /// radix
     fn eat_digit(&mut self, radix: u32) -> Option<u32> {
         trace!("eat_digit {:?}", self.current(),);
-        if let Some(next) = self.chars.peek() {
+        if let Some(next) = self.peeked.as_ref() {
             if next.is_digit(radix) {
                 let n = next.to_digit(radix);
                 self.advance();
@@ -721,23 +2678,47 @@ impl<'a> RegexParser<'a> {
             }
             return false;
         }
-        if let Some(ch) = self.chars.peek() {
-            if *ch != 'c' && (!self.state.n || *ch != 'k') {
-                let n = (*ch).into();
-                self.state.last_int_value = Some(n);
+        if self.state.strict {
+            if let Some(ch) = self.peeked.as_ref() {
+                if !Self::is_id_continue(*ch) {
+                    self.state.last_int_value = Some((*ch).into());
+                    self.advance();
+                    return true;
+                }
+            }
+            return false;
+        }
+        if let Some(&ch) = self.peeked.as_ref() {
+            if ch != 'c' && (!self.state.n || ch != 'k') {
+                if ch == 'k' {
+                    self.state.n_dependent = true;
+                }
+                if ch == 'p' || ch == 'P' {
+                    self.warn(
+                        self.state.pos - 1,
+                        "property escapes have no special meaning without the u flag",
+                    );
+                } else if ch.is_ascii_alphabetic() {
+                    self.warn(
+                        self.state.pos - 1,
+                        &format!("unnecessary escape \\{0} matches '{0}'", ch),
+                    );
+                }
+                self.state.last_int_value = Some(ch.into());
                 self.advance();
                 true
             } else {
                 false
             }
         } else {
-            true
+            // a `\` at the end of the pattern escapes nothing
+            false
         }
     }
     /// Attempt to consume a syntax character like `{`
     fn eat_syntax_character(&mut self) -> bool {
         trace!("eat_syntax_character {:?}", self.current(),);
-        if let Some(ch) = self.chars.peek() {
+        if let Some(ch) = self.peeked.as_ref() {
             if Self::is_syntax_ch(*ch) {
                 self.state.last_int_value = Some((*ch).into());
                 self.advance();
@@ -756,11 +2737,11 @@ impl<'a> RegexParser<'a> {
         if self.eat('u') {
             if self.eat_fixed_hex_digits(4) {
                 let lead = self.state.last_int_value.unwrap_or(0);
-                if self.state.u && lead >= 0xD800 && lead <= 0xDBFF {
+                if self.state.u && (0xD800..=0xDBFF).contains(&lead) {
                     let lead_end = self.state.pos;
                     if self.eat('\\') && self.eat('u') && self.eat_fixed_hex_digits(4) {
                         let tail = self.state.last_int_value.unwrap_or(0);
-                        if tail >= 0xDC00 && tail <= 0xDFFF {
+                        if (0xDC00..=0xDFFF).contains(&tail) {
                             self.state.last_int_value =
                                 Some((lead - 0xD800) * 0x400 + (tail - 0xDC00) + 0x10000);
                             return Ok(true);
@@ -771,9 +2752,23 @@ impl<'a> RegexParser<'a> {
                 }
                 return Ok(true);
             }
+            let brace = self.state.pos;
+            if self.state.u && self.eat('{') && !self.eat_digits(16) {
+                if self.current() == Some(&'}') {
+                    return Err(Error::new(
+                        brace,
+                        ErrorKind::InvalidEscape,
+                        "Empty unicode escape",
+                    ));
+                }
+                return Err(Error::new(
+                    brace,
+                    ErrorKind::InvalidEscape,
+                    "Invalid unicode escape, expected hex digits after `{`",
+                ));
+            }
             if self.state.u
-                && self.eat('{')
-                && self.eat_digits(16)
+                && self.state.pos > brace
                 && self.eat('}')
                 && self
                     .state
@@ -785,7 +2780,11 @@ impl<'a> RegexParser<'a> {
             }
 
             if self.state.u {
-                return Err(Error::new(self.state.pos, "Invalid unicode escape"));
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::InvalidEscape,
+                    "Invalid unicode escape",
+                ));
             }
 
             self.reset_to(start)
@@ -798,45 +2797,437 @@ impl<'a> RegexParser<'a> {
     /// ```
     fn eat_character_class(&mut self) -> Result<bool, Error> {
         trace!("eat_character_class {:?}", self.current(),);
+        if self.state.v {
+            return Ok(self.eat_class_set_class()?.is_some());
+        }
+        let start = self.state.pos;
         if self.eat('[') {
-            self.eat('^');
-            self.class_ranges()?;
+            let negated = self.eat('^');
+            self.push_token(TokenKind::ClassOpen, start);
+            let ranges = self.class_ranges()?;
+            let end = self.state.pos;
             if self.eat(']') {
+                if !negated && end == start + 1 {
+                    self.warn(start, "an empty character class never matches");
+                }
+                self.push_token(TokenKind::ClassClose, end);
+                self.state.classes.push(ClassInfo {
+                    span: (start, self.state.pos),
+                    ranges: if negated { None } else { ranges },
+                });
                 Ok(true)
             } else {
-                Err(Error::new(self.state.pos, "Unterminated character class"))
+                Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::UnterminatedClass,
+                    "Unterminated character class",
+                ))
             }
         } else {
             Ok(false)
         }
     }
+    /// Attempt to consume a character class when the `v` flag
+    /// is set, returning if the class may contain strings
+    /// ```js
+    /// let re = /[\p{RGI_Emoji}--\q{🇺🇸}]/v;
+    /// ```
+    fn eat_class_set_class(&mut self) -> Result<Option<bool>, Error> {
+        trace!("eat_class_set_class {:?}", self.current(),);
+        let start = self.state.pos;
+        if !self.eat('[') {
+            return Ok(None);
+        }
+        let negated = self.eat('^');
+        self.push_token(TokenKind::ClassOpen, start);
+        self.enter_nested()?;
+        let (strings, ranges) = self.class_set_expression()?;
+        self.state.depth -= 1;
+        if negated && strings {
+            return Err(Error::new(
+                start,
+                ErrorKind::InvalidCharacterClass,
+                "Negated character class may contain strings",
+            ));
+        }
+        let end = self.state.pos;
+        if !self.eat(']') {
+            if self.current().is_some() {
+                return Err(Error::new(
+                    end,
+                    ErrorKind::InvalidCharacterClass,
+                    "Invalid character in character class",
+                ));
+            }
+            return Err(Error::new(
+                end,
+                ErrorKind::UnterminatedClass,
+                "Unterminated character class",
+            ));
+        }
+        if !negated && end == start + 1 {
+            self.warn(start, "an empty character class never matches");
+        }
+        self.push_token(TokenKind::ClassClose, end);
+        self.state.classes.push(ClassInfo {
+            span: (start, self.state.pos),
+            ranges: if negated { None } else { ranges },
+        });
+        Ok(Some(strings))
+    }
+    /// The contents of a class when the `v` flag is set, either
+    /// a union of operands and ranges or operands joined by
+    /// `&&` (intersection) or `--` (subtraction)
+    ///
+    /// This will return if the class may contain strings along
+    /// with its ranges, like `class_ranges`
+    fn class_set_expression(&mut self) -> Result<(bool, ClassRanges), Error> {
+        trace!("class_set_expression {:?}", self.current(),);
+        let first = self.state.pos;
+        let mut strings = false;
+        let mut ranges = Some(Vec::new());
+        loop {
+            self.check_budget()?;
+            let item_start = self.state.pos;
+            let item_strings = match self.eat_class_set_operand()? {
+                Some(item_strings) => item_strings,
+                None => break,
+            };
+            let left = self.state.last_int_value;
+            if self.at_set_operator() {
+                if item_start == first {
+                    return self.class_set_operation(item_strings);
+                }
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::InvalidCharacterClass,
+                    "Invalid set operation in character class",
+                ));
+            }
+            let left_start = self.state.tokens.last().map(|t| t.span.0).unwrap_or(0);
+            let dash = self.state.pos;
+            if left.is_some() && self.eat('-') {
+                self.push_token(TokenKind::ClassRange, dash);
+                // a class escape or `\q{...}` can't be the end of a range
+                let rest = &self.pattern[self.state.pos..];
+                let class_end =
+                    rest.starts_with('\\') && rest[1..].starts_with(|ch| "dDsSwWpPq".contains(ch));
+                if class_end || !self.eat_class_set_character()? {
+                    return Err(Error::new(
+                        dash,
+                        ErrorKind::InvalidCharacterClass,
+                        "Invalid character class",
+                    ));
+                }
+                let (left, right) = (left.unwrap_or(0), self.state.last_int_value.unwrap_or(0));
+                if left > right {
+                    return Err(Error::new(
+                        self.state.pos,
+                        ErrorKind::RangeOutOfOrder,
+                        &format!(
+                            "Range out of order in character class ({} > {})",
+                            left, right
+                        ),
+                    ));
+                }
+                if self.state.case_insensitive && Self::range_folds(left, right) {
+                    self.warn(
+                        left_start,
+                        "with the i and u flags this range also matches non-ASCII characters \
+                        through Unicode case folding (like the Kelvin sign for k)",
+                    );
+                }
+                Self::push_class_range(&mut ranges, Some(left), Some(right));
+                if self.at_set_operator() {
+                    return Err(Error::new(
+                        self.state.pos,
+                        ErrorKind::InvalidCharacterClass,
+                        "Invalid set operation in character class",
+                    ));
+                }
+            } else {
+                strings |= item_strings;
+                Self::push_class_range(&mut ranges, left, left);
+            }
+        }
+        Ok((strings, ranges))
+    }
+    /// `true` if the next two characters are `&&` or `--`
+    fn at_set_operator(&self) -> bool {
+        let rest = &self.pattern[self.state.pos..];
+        rest.starts_with("&&") || rest.starts_with("--")
+    }
+    /// Consume the rest of an intersection or subtraction after
+    /// the first operand, the result may contain strings when all
+    /// operands of an intersection or the first operand of a
+    /// subtraction may
+    fn class_set_operation(&mut self, first_strings: bool) -> Result<(bool, ClassRanges), Error> {
+        trace!("class_set_operation {:?}", self.current(),);
+        let intersection = self.pattern[self.state.pos..].starts_with("&&");
+        let op = if intersection { "&&" } else { "--" };
+        let mut strings = first_strings;
+        while self.pattern[self.state.pos..].starts_with(op) {
+            self.check_budget()?;
+            self.advance();
+            self.advance();
+            if intersection && self.current() == Some(&'&') {
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::InvalidCharacterClass,
+                    "Invalid set operation in character class",
+                ));
+            }
+            match self.eat_class_set_operand()? {
+                Some(operand_strings) if intersection => strings &= operand_strings,
+                Some(_) => {}
+                None => {
+                    return Err(Error::new(
+                        self.state.pos,
+                        ErrorKind::InvalidCharacterClass,
+                        "Invalid set operation in character class",
+                    ))
+                }
+            }
+        }
+        if self.current().is_some() && self.current() != Some(&']') {
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::InvalidCharacterClass,
+                "Invalid set operation in character class",
+            ));
+        }
+        Ok((strings, None))
+    }
+    /// Attempt to consume a nested class, a class escape, a
+    /// `\q{...}` or a single character, returning if the
+    /// operand may contain strings. `last_int_value` will be
+    /// set only for a single character
+    fn eat_class_set_operand(&mut self) -> Result<Option<bool>, Error> {
+        trace!("eat_class_set_operand {:?}", self.current(),);
+        let start = self.state.pos;
+        if let Some(strings) = self.eat_class_set_class()? {
+            self.state.last_int_value = None;
+            return Ok(Some(strings));
+        }
+        if self.eat('\\') {
+            if self.eat('q') {
+                if !self.eat('{') {
+                    return Err(Error::new(
+                        self.state.pos,
+                        ErrorKind::InvalidEscape,
+                        "Invalid escape",
+                    ));
+                }
+                let (tokens, escapes) = (self.state.tokens.len(), self.state.escapes.len());
+                let strings = self.class_string_disjunction()?;
+                // the whole `\q{...}` is recorded as one escape
+                self.state.tokens.truncate(tokens);
+                self.state.escapes.truncate(escapes);
+                self.state.last_int_value = None;
+                self.push_escape(start);
+                return Ok(Some(strings));
+            }
+            if self.eat_character_class_escape()? {
+                let raw = &self.pattern[start..self.state.pos];
+                let strings = raw.starts_with("\\p{")
+                    && unicode::is_property_of_strings(&raw[3..raw.len() - 1]);
+                self.push_escape(start);
+                return Ok(Some(strings));
+            }
+            self.reset_to(start);
+        }
+        Ok(if self.eat_class_set_character()? {
+            Some(false)
+        } else {
+            None
+        })
+    }
+    /// Consume the body of a `\q{...}` after the `{`, returning
+    /// if any of the strings isn't a single character
+    /// ```js
+    /// let re = /[\q{abc|d}]/v;
+    /// ```
+    fn class_string_disjunction(&mut self) -> Result<bool, Error> {
+        trace!("class_string_disjunction {:?}", self.current(),);
+        let mut strings = false;
+        loop {
+            let mut len = 0;
+            while self.eat_class_set_character()? {
+                len += 1;
+            }
+            strings |= len != 1;
+            if !self.eat('|') {
+                break;
+            }
+        }
+        if !self.eat('}') {
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::InvalidCharacterClass,
+                "Unterminated class string",
+            ));
+        }
+        Ok(strings)
+    }
+    /// Attempt to consume a single character of a class when
+    /// the `v` flag is set, the syntax characters `()[]{}/-\|`
+    /// need to be escaped and doubled punctuators like `&&` or
+    /// `!!` are reserved
+    fn eat_class_set_character(&mut self) -> Result<bool, Error> {
+        trace!("eat_class_set_character {:?}", self.current(),);
+        let start = self.state.pos;
+        if self.eat('\\') {
+            if self.eat('b') {
+                self.state.last_int_value = Some(0x08);
+            } else if let Some(ch) = self.peeked.filter(|ch| "&-!#%,:;<=>@`~".contains(*ch)) {
+                self.advance();
+                self.state.last_int_value = Some(ch.into());
+            } else if !self.eat_character_escape()? {
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::InvalidEscape,
+                    "Invalid escape",
+                ));
+            }
+            self.push_escape(start);
+            return Ok(true);
+        }
+        let ch = match self.peeked.as_ref() {
+            Some(&ch) if !"()[]{}/-\\|".contains(ch) => ch,
+            _ => return Ok(false),
+        };
+        if "&!#$%*+,.:;<=>?@^`~".contains(ch)
+            && self.pattern[start + ch.len_utf8()..].starts_with(ch)
+        {
+            return Err(Error::new(
+                start,
+                ErrorKind::InvalidCharacterClass,
+                "Invalid set operation in character class",
+            ));
+        }
+        self.advance();
+        self.state.last_int_value = Some(ch.into());
+        self.push_token(TokenKind::Literal, start);
+        Ok(true)
+    }
     /// Attempt to consume a class range
     /// ```js
     /// let re = /[c-r]/;
     /// ```
-    fn class_ranges(&mut self) -> Result<(), Error> {
+    ///
+    /// This will return the inclusive ranges of code points
+    /// in the class or `None` if the class includes something
+    /// that cannot be enumerated (like `\w`)
+    fn class_ranges(&mut self) -> Result<Option<Vec<(u32, u32)>>, Error> {
         trace!("class_ranges {:?}", self.current(),);
+        let mut ranges = Some(Vec::new());
+        // the single characters so far, to find duplicates
+        let mut singles = Vec::new();
         while self.eat_class_atom()? {
+            self.check_budget()?;
             let left = self.state.last_int_value;
-            if self.eat('-') && self.eat_class_atom()? {
+            let left_start = self.state.tokens.last().map(|t| t.span.0).unwrap_or(0);
+            let dash = self.state.pos;
+            if self.eat('-') {
+                let token_idx = self.state.tokens.len();
+                let right_found = self.eat_class_atom()?;
+                self.state.tokens.insert(
+                    token_idx,
+                    Token {
+                        kind: if right_found {
+                            TokenKind::ClassRange
+                        } else {
+                            TokenKind::Literal
+                        },
+                        span: (dash, dash + 1),
+                    },
+                );
+                if !right_found {
+                    Self::push_class_range(&mut ranges, left, left);
+                    Self::push_class_range(&mut ranges, Some(0x2D), Some(0x2D));
+                    continue;
+                }
                 let right = self.state.last_int_value;
-                if self.state.u && (left.is_none() || right.is_none()) {
-                    return Err(Error::new(self.state.pos, "Invalid character class"));
+                if (self.state.u || self.state.strict) && (left.is_none() || right.is_none()) {
+                    return Err(Error::new(
+                        dash,
+                        ErrorKind::InvalidCharacterClass,
+                        "Invalid character class",
+                    ));
                 }
                 if let (Some(left), Some(right)) = (left, right) {
                     if left > right {
                         return Err(Error::new(
                             self.state.pos,
+                            ErrorKind::RangeOutOfOrder,
                             &format!(
                                 "Range out of order in character class ({} > {})",
                                 left, right
                             ),
                         ));
                     }
+                    if self.state.u && self.state.case_insensitive && Self::range_folds(left, right)
+                    {
+                        self.warn(
+                            left_start,
+                            "with the i and u flags this range also matches non-ASCII characters \
+                            through Unicode case folding (like the Kelvin sign for k)",
+                        );
+                    }
+                    if Self::range_spans_case_gap(left, right) {
+                        let span = (left_start, self.state.pos);
+                        let msg = format!(
+                            "range {} also matches the punctuation between Z and a ([\\]^_`)",
+                            &self.pattern[span.0..span.1]
+                        );
+                        self.warn_span(span, &msg);
+                    }
+                    Self::push_class_range(&mut ranges, Some(left), Some(right));
+                } else {
+                    Self::push_class_range(&mut ranges, left, left);
+                    Self::push_class_range(&mut ranges, Some(0x2D), Some(0x2D));
+                    Self::push_class_range(&mut ranges, right, right);
+                }
+            } else {
+                if let Some(ch) = left {
+                    if singles.contains(&ch) {
+                        let raw = &self.pattern[left_start..self.state.pos];
+                        self.warn(left_start, &format!("duplicate {} in character class", raw));
+                    } else {
+                        singles.push(ch);
+                    }
                 }
+                Self::push_class_range(&mut ranges, left, left);
             }
         }
-        Ok(())
+        Ok(ranges)
+    }
+    /// `true` if an ASCII range includes `k` or `s` (in either case),
+    /// these fold to `K` (U+212A) and `ſ` (U+017F) under `u` and `i`
+    fn range_folds(left: u32, right: u32) -> bool {
+        right < 0x80
+            && [0x4B, 0x53, 0x6B, 0x73]
+                .iter()
+                .any(|ch| (left..=right).contains(ch))
+    }
+    /// `true` if a range from a digit or upper case letter to a
+    /// lower case letter (like `A-z`), which also includes the
+    /// punctuation between `Z` (0x5A) and `a` (0x61)
+    fn range_spans_case_gap(left: u32, right: u32) -> bool {
+        ((0x30..=0x39).contains(&left) || (0x41..=0x5A).contains(&left))
+            && (0x61..=0x7A).contains(&right)
+    }
+    /// Add a range to the list of class ranges, if either
+    /// side is `None` the list can no longer be enumerated
+    fn push_class_range(
+        ranges: &mut Option<Vec<(u32, u32)>>,
+        left: Option<u32>,
+        right: Option<u32>,
+    ) {
+        match (ranges.as_mut(), left, right) {
+            (Some(list), Some(left), Some(right)) => list.push((left, right)),
+            _ => *ranges = None,
+        }
     }
     /// Attempt to consume a single part of a class
     fn eat_class_atom(&mut self) -> Result<bool, Error> {
@@ -844,22 +3235,38 @@ impl<'a> RegexParser<'a> {
         let start = self.state.pos;
         if self.eat('\\') {
             if self.eat_class_escape()? {
+                self.push_escape(start);
                 return Ok(true);
             }
-            if self.state.u {
-                if let Some(ch) = self.chars.peek() {
+            if self.state.u || self.state.strict {
+                if let Some(ch) = self.peeked.as_ref() {
                     if *ch == 'c' || ch.is_digit(8) {
-                        return Err(Error::new(self.state.pos, "Invalid class escape"));
+                        return Err(Error::new(
+                            self.state.pos,
+                            ErrorKind::InvalidEscape,
+                            "Invalid class escape",
+                        ));
                     }
-                    return Err(Error::new(self.state.pos, "Invalid escape"));
+                    return Err(Error::new(
+                        self.state.pos,
+                        ErrorKind::InvalidEscape,
+                        "Invalid escape",
+                    ));
                 }
             }
             self.reset_to(start);
         }
-        if let Some(ch) = self.chars.peek() {
-            if *ch != ']' {
-                self.state.last_int_value = Some((*ch).into());
+        if let Some(&ch) = self.peeked.as_ref() {
+            if ch != ']' {
+                if ch == '.' {
+                    self.warn(
+                        start,
+                        "'.' inside a character class matches a literal dot, not any character",
+                    );
+                }
+                self.state.last_int_value = Some(ch.into());
                 self.advance();
+                self.push_token(TokenKind::Literal, start);
                 return Ok(true);
             }
         }
@@ -889,8 +3296,8 @@ impl<'a> RegexParser<'a> {
     /// attempt to consume a control letter
     fn eat_class_control_letter(&mut self) -> bool {
         trace!("eat_class_control_letter {:?}", self.current(),);
-        if let Some(ch) = self.chars.peek() {
-            if ch.is_digit(10) || *ch == '_' {
+        if let Some(ch) = self.peeked.as_ref() {
+            if ch.is_ascii_digit() || *ch == '_' {
                 let n: u32 = (*ch).into();
                 self.state.last_int_value = Some(n % 0x20);
                 self.advance();
@@ -902,14 +3309,31 @@ impl<'a> RegexParser<'a> {
     /// attempt to consume a `\k` group
     fn eat_k_group_name(&mut self) -> Result<bool, Error> {
         trace!("eat_k_group_name {:?}", self.current(),);
+        let start = self.state.pos;
         if self.eat('k') {
+            if self.pattern[self.state.pos..].starts_with("<>") {
+                return Err(Error::new(
+                    self.state.pos + 1,
+                    ErrorKind::InvalidNamedReference,
+                    "Empty group name in reference",
+                ));
+            }
             if self.eat_group_name()? {
                 if let Some(name) = self.state.last_string_value {
-                    self.state.back_ref_names.push(name);
+                    // include the `\` that came before the `k`
+                    self.state.back_ref_names.push((name, start - 1));
+                    self.state.back_refs.push(BackRefSpan::Named {
+                        name,
+                        span: (start - 1, self.state.pos),
+                    });
                     return Ok(true);
                 }
             }
-            return Err(Error::new(self.state.pos, "Invalid named reference"));
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::InvalidNamedReference,
+                "Invalid named reference",
+            ));
         }
         Ok(false)
     }
@@ -921,7 +3345,18 @@ impl<'a> RegexParser<'a> {
             if self.eat_regex_identifier_name()? && self.eat('>') {
                 return Ok(true);
             }
-            return Err(Error::new(self.state.pos, "Invalid capture group name"));
+            if self.current() == Some(&'>') {
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::InvalidGroupName,
+                    "Empty capture group name",
+                ));
+            }
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::InvalidGroupName,
+                "Invalid capture group name",
+            ));
         }
         Ok(false)
     }
@@ -932,7 +3367,7 @@ impl<'a> RegexParser<'a> {
         self.state.last_string_value = None;
         if self.eat_ident_start()? {
             while self.eat_ident_part()? {}
-            self.state.last_string_value = Some(&self.pattern[start..self.state.pos]);
+            self.state.last_string_value = Some((start, self.state.pos));
             return Ok(true);
         }
         Ok(false)
@@ -942,17 +3377,15 @@ impl<'a> RegexParser<'a> {
         trace!("eat_ident_start {:?}", self.current(),);
         let start = self.state.pos;
         self.state.last_string_value = None;
-        let mut ch = if let Some(ch) = self.chars.peek() {
+        let mut ch = if let Some(ch) = self.peeked.as_ref() {
             *ch
         } else {
             return Ok(false);
         };
         self.advance();
         if ch == '\\' && self.eat_unicode_escape_sequence()? {
-            if let Some(n) = self.state.last_int_value {
-                if let Some(n) = std::char::from_u32(n) {
-                    ch = n;
-                }
+            if let Some(n) = self.escaped_ident_char(start)? {
+                ch = n;
             }
         }
         if Self::is_id_start(ch) {
@@ -966,17 +3399,15 @@ impl<'a> RegexParser<'a> {
     fn eat_ident_part(&mut self) -> Result<bool, Error> {
         trace!("eat_ident_part {:?}", self.current(),);
         let start = self.state.pos;
-        let mut ch = if let Some(ch) = self.chars.peek() {
+        let mut ch = if let Some(ch) = self.peeked.as_ref() {
             *ch
         } else {
             return Ok(false);
         };
         self.advance();
         if ch == '\\' && self.eat_unicode_escape_sequence()? {
-            if let Some(n) = self.state.last_int_value {
-                if let Some(n) = std::char::from_u32(n) {
-                    ch = n;
-                }
+            if let Some(n) = self.escaped_ident_char(start)? {
+                ch = n;
             }
         }
         if Self::is_id_continue(ch) {
@@ -986,70 +3417,187 @@ impl<'a> RegexParser<'a> {
         self.reset_to(start);
         Ok(false)
     }
+    /// The character of the `\u` escape just consumed in an
+    /// identifier starting at `start`, with the `u` flag a
+    /// surrogate is an error since a pair has already been
+    /// combined, so this would be a lone surrogate
+    fn escaped_ident_char(&self, start: usize) -> Result<Option<char>, Error> {
+        let n = match self.state.last_int_value {
+            Some(n) => n,
+            None => return Ok(None),
+        };
+        if self.state.u && (0xD800..=0xDFFF).contains(&n) {
+            return Err(Error::new(
+                start,
+                ErrorKind::InvalidGroupName,
+                "Invalid surrogate in identifier",
+            ));
+        }
+        Ok(core::char::from_u32(n))
+    }
 
     fn is_id_start(ch: char) -> bool {
-        (ch >= 'A' && ch <= 'Z')
-            || (ch >= 'a' && ch <= 'z')
-            || ch == '$'
-            || ch == '_'
-            || unic_ucd_ident::is_id_start(ch)
+        ch.is_ascii_alphabetic() || ch == '$' || ch == '_' || unic_ucd_ident::is_id_start(ch)
     }
 
     fn is_id_continue(ch: char) -> bool {
-        (ch >= 'A' && ch <= 'Z')
-            || (ch >= 'a' && ch <= 'z')
-            || (ch >= '0' && ch <= '9')
-            || ch == '$'
-            || ch == '_'
-            || unic_ucd_ident::is_id_continue(ch)
+        ch.is_ascii_alphanumeric() || ch == '$' || ch == '_' || unic_ucd_ident::is_id_continue(ch)
     }
 
     fn eat_uncapturing_group(&mut self) -> Result<bool, Error> {
         trace!("eat_uncapturing_group {:?}", self.current(),);
         let start = self.state.pos;
         if self.eat('(') {
-            if self.eat('?') && self.eat(':') {
-                self.disjunction()?;
+            if self.eat('?') && (self.eat(':') || self.eat_modifiers()?) {
+                self.push_token(TokenKind::GroupOpen, start);
+                self.nested_disjunction()?;
+                let end = self.state.pos;
                 if self.eat(')') {
+                    self.push_token(TokenKind::GroupClose, end);
                     return Ok(true);
                 }
-                return Err(Error::new(start, "Unterminated group"));
+                return Err(Error::new(
+                    start,
+                    ErrorKind::UnterminatedGroup,
+                    "Unterminated group",
+                ));
             }
             self.reset_to(start)
         }
         Ok(false)
     }
+    /// Picking up after a `(?`, attempt to consume the flags
+    /// of a modifier group (like `i-m:`) including the `:`
+    fn eat_modifiers(&mut self) -> Result<bool, Error> {
+        trace!("eat_modifiers {:?}", self.current(),);
+        match self.peeked.as_ref() {
+            Some(ch) if ch.is_ascii_alphabetic() || *ch == '-' => {}
+            _ => return Ok(false),
+        }
+        self.require_version(self.state.pos - 2, EcmaVersion::Es2025, "Modifier groups")?;
+        let mut seen = String::new();
+        let mut removing = false;
+        loop {
+            match self.peeked.as_ref().copied() {
+                Some(':') => break,
+                Some('-') if !removing => removing = true,
+                Some(ch) if "ims".contains(ch) => {
+                    if seen.contains(ch) {
+                        return Err(Error::new(
+                            self.state.pos,
+                            ErrorKind::DuplicateFlag,
+                            &format!("duplicate {} flag", ch),
+                        ));
+                    }
+                    seen.push(ch);
+                }
+                Some(ch) if ch.is_ascii_alphabetic() => {
+                    return Err(Error::new(
+                        self.state.pos,
+                        ErrorKind::InvalidGroup,
+                        "Invalid group flags",
+                    ))
+                }
+                _ => {
+                    return Err(Error::new(
+                        self.state.pos,
+                        ErrorKind::InvalidGroup,
+                        "Invalid group",
+                    ))
+                }
+            }
+            self.advance();
+        }
+        // `(?-:` has nothing to add or remove
+        if seen.is_empty() {
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::InvalidGroup,
+                "Invalid group flags",
+            ));
+        }
+        self.advance();
+        Ok(true)
+    }
 
     fn eat_capturing_group(&mut self) -> Result<bool, Error> {
         trace!("eat_capturing_group {:?}", self.current(),);
+        let start = self.state.pos;
         if self.eat('(') {
-            self.group_specifier()?;
-            self.disjunction()?;
+            if self.state.require_named_groups && self.current() != Some(&'?') {
+                return Err(Error::new(
+                    start,
+                    ErrorKind::InvalidGroupName,
+                    "Anonymous capturing group; use (?<name>...) or (?:...)",
+                ));
+            }
+            self.group_specifier(start)?;
+            let name = match self.state.group_names.last() {
+                Some((name, idx)) if *idx == start => Some(*name),
+                _ => None,
+            };
+            let group = self.state.groups.len();
+            self.state.groups.push(GroupSpan {
+                index: group as u32 + 1,
+                name,
+                span: (start, start),
+            });
+            self.push_token(TokenKind::GroupOpen, start);
+            self.nested_disjunction()?;
+            let end = self.state.pos;
             if self.eat(')') {
+                self.push_token(TokenKind::GroupClose, end);
+                self.state.groups[group].span.1 = self.state.pos;
                 self.state.num_capturing_parens += 1;
                 Ok(true)
             } else {
-                Err(Error::new(self.state.pos, "Unterminated group"))
+                Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::UnterminatedGroup,
+                    "Unterminated group",
+                ))
             }
         } else {
             Ok(false)
         }
     }
 
-    fn group_specifier(&mut self) -> Result<(), Error> {
+    /// `start` is the position of the group's `(`, which is
+    /// recorded alongside any name for error reporting
+    fn group_specifier(&mut self, start: usize) -> Result<(), Error> {
         trace!("group_specifier {:?}", self.current(),);
         if self.eat('?') {
             if self.eat_group_name()? {
-                if let Some(name) = self.state.last_string_value {
-                    if self.state.group_names.contains(&name) {
-                        return Err(Error::new(self.state.pos, "Duplicate capture group name"));
+                self.require_version(start, EcmaVersion::Es2018, "Named groups")?;
+                if let (Some(name), Some(text)) = (
+                    self.state.last_string_value,
+                    self.state.last_string(&self.pattern),
+                ) {
+                    if let Some((_, first)) = self
+                        .state
+                        .group_names
+                        .iter()
+                        .find(|(n, _)| self.text_at(*n) == text)
+                    {
+                        return Err(Error::new(
+                            self.state.pos,
+                            ErrorKind::DuplicateGroupName,
+                            &format!(
+                                "Duplicate capture group name '{}' (first defined at {})",
+                                text, first
+                            ),
+                        ));
                     } else {
-                        self.state.group_names.push(name);
+                        self.state.group_names.push((name, start));
                         return Ok(());
                     }
                 }
             }
-            return Err(Error::new(self.state.pos, "Invalid group"));
+            return Err(Error::new(
+                self.state.pos,
+                ErrorKind::InvalidGroup,
+                "Invalid group",
+            ));
         }
         Ok(())
     }
@@ -1059,10 +3607,12 @@ impl<'a> RegexParser<'a> {
         let start = self.state.pos;
         self.state.last_assert_is_quant = false;
         if self.eat('^') || self.eat('$') {
+            self.push_token(TokenKind::Assertion, start);
             return Ok(true);
         }
         if self.eat('\\') {
             if self.eat('B') || self.eat('b') {
+                self.push_token(TokenKind::Assertion, start);
                 return Ok(true);
             }
             self.reset_to(start);
@@ -1070,11 +3620,28 @@ impl<'a> RegexParser<'a> {
         if self.eat('(') && self.eat('?') {
             let look_behind = self.eat('<');
             if self.eat('=') || self.eat('!') {
-                self.disjunction()?;
+                if look_behind {
+                    self.require_version(start, EcmaVersion::Es2018, "Look behind assertions")?;
+                }
+                self.push_token(TokenKind::GroupOpen, start);
+                if look_behind {
+                    self.state.lookbehind_depth += 1;
+                }
+                self.nested_disjunction()?;
+                if look_behind {
+                    self.state.lookbehind_depth -= 1;
+                }
+                let end = self.state.pos;
                 if !self.eat(')') {
-                    return Err(Error::new(self.state.pos, "Unterminated group"));
+                    return Err(Error::new(
+                        self.state.pos,
+                        ErrorKind::UnterminatedGroup,
+                        "Unterminated group",
+                    ));
                 }
+                self.push_token(TokenKind::GroupClose, end);
                 self.state.last_assert_is_quant = !look_behind;
+                self.state.uses_lookaround = true;
                 return Ok(true);
             }
         }
@@ -1082,295 +3649,3178 @@ impl<'a> RegexParser<'a> {
         Ok(false)
     }
 
+    /// Error if the target edition is older than `version`,
+    /// which introduced `feature`
+    fn require_version(
+        &self,
+        idx: usize,
+        version: EcmaVersion,
+        feature: &str,
+    ) -> Result<(), Error> {
+        if self.state.ecma_version < version {
+            return Err(Error::new(
+                idx,
+                ErrorKind::NewerThanTarget,
+                &format!("{} require {}", feature, version),
+            ));
+        }
+        Ok(())
+    }
+    /// Compare two strings of decimal digits by their value
+    fn cmp_decimal(left: &str, right: &str) -> Ordering {
+        let left = left.trim_start_matches('0');
+        let right = right.trim_start_matches('0');
+        left.len().cmp(&right.len()).then_with(|| left.cmp(right))
+    }
+    /// Consume digits in `radix`, the value in `last_int_value`
+    /// is clamped to `u32::MAX`
     fn eat_digits(&mut self, radix: u32) -> bool {
         trace!("eat_digits {:?}", self.current(),);
         let start = self.state.pos;
         self.state.last_int_value = Some(0);
-        while let Some(next) = self.chars.peek() {
+        while let Some(next) = self.peeked.as_ref() {
             log::debug!("next digit: {}", next);
             if let Some(n) = next.to_digit(radix) {
                 log::debug!("digit as u32: {}", n);
                 let last_int_value = self.state.last_int_value.unwrap_or(0);
                 log::debug!("last_int_value: {}", last_int_value);
-                self.state.last_int_value = Some(radix * last_int_value + n);
+                self.state.last_int_value =
+                    Some(last_int_value.saturating_mul(radix).saturating_add(n));
                 self.advance();
             } else {
                 log::debug!("next not digit");
                 break;
             }
         }
-        self.state.pos != start
+        self.state.pos != start
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        self.state.operations += 1;
+        if let Some(next) = self.peeked.as_ref() {
+            if *next == ch {
+                self.advance();
+                return true;
+            }
+        }
+        false
+    }
+
+    fn advance(&mut self) {
+        self.state.operations += 1;
+        if let Some(ch) = self.peeked {
+            self.state.pos += ch.len_utf8();
+            self.peeked = self.pattern[self.state.pos..].chars().next();
+            log::debug!("adv: {} ({})", ch, self.state.pos);
+        } else {
+            log::debug!("adv at end");
+        }
+    }
+
+    fn reset_to(&mut self, idx: usize) {
+        self.state.operations += 1;
+        // positions are byte offsets so a reset into the middle
+        // of a character means one was tracked incorrectly
+        debug_assert!(
+            self.pattern.is_char_boundary(idx),
+            "reset_to {} is not a char boundary in {:?}",
+            idx,
+            self.pattern
+        );
+        // in a release build a bad position ends the input early,
+        // which is reported as an error instead of a panic
+        self.peeked = self.pattern.get(idx..).and_then(|rest| rest.chars().next());
+        log::debug!("res: {} ({})", self.peeked.unwrap_or(' '), idx);
+        self.state.pos = idx;
+    }
+    /// Record an escape that started at `start` and
+    /// ends at the current position
+    fn push_escape(&mut self, start: usize) {
+        let raw = &self.pattern[start + 1..self.state.pos];
+        let back_reference = self.state.last_int_value.is_none()
+            && (raw.starts_with(|ch: char| ch.is_ascii_digit()) || raw.starts_with("k<"));
+        self.state.escapes.push(EscapeInfo {
+            span: (start, self.state.pos),
+            value: self.state.last_int_value,
+            back_reference,
+        });
+        self.push_token(TokenKind::Escape, start);
+    }
+    /// Record a token that started at `start` and
+    /// ends at the current position
+    fn push_token(&mut self, kind: TokenKind, start: usize) {
+        self.state.tokens.push(Token {
+            kind,
+            span: (start, self.state.pos),
+        });
+    }
+    /// Record a warning if they are being collected
+    fn warn(&mut self, idx: usize, msg: &str) {
+        if self.state.collect_warnings {
+            self.state.warnings.push(Warning {
+                idx,
+                span: None,
+                msg: msg.to_string(),
+            });
+        }
+    }
+    /// Like `warn` for a construct that covers `span`
+    fn warn_span(&mut self, span: (usize, usize), msg: &str) {
+        if self.state.collect_warnings {
+            self.state.warnings.push(Warning {
+                idx: span.0,
+                span: Some(span),
+                msg: msg.to_string(),
+            });
+        }
+    }
+    /// Fail if the number of operations performed has
+    /// exceeded the configured `max_operations`
+    fn check_budget(&self) -> Result<(), Error> {
+        if let Some(max) = self.state.max_operations {
+            if self.state.operations > max {
+                return Err(Error::new(
+                    self.state.pos,
+                    ErrorKind::BudgetExceeded,
+                    "Parse budget exceeded",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `GroupInfo` with the name as a span in the pattern
+#[derive(Debug, Clone, Copy)]
+struct GroupSpan {
+    index: u32,
+    name: Option<(usize, usize)>,
+    span: (usize, usize),
+}
+
+/// A `BackRef` with the name as a span in the pattern
+#[derive(Debug, Clone, Copy)]
+enum BackRefSpan {
+    Numeric {
+        index: u32,
+        span: (usize, usize),
+    },
+    Named {
+        name: (usize, usize),
+        span: (usize, usize),
+    },
+}
+
+/// A character class found while validating
+#[derive(Clone)]
+struct ClassInfo {
+    span: (usize, usize),
+    ranges: Option<Vec<(u32, u32)>>,
+}
+
+/// The inclusive ranges of code points in a class, `None`
+/// if the class can't be enumerated
+type ClassRanges = Option<Vec<(u32, u32)>>;
+
+#[derive(Clone)]
+struct State {
+    pos: usize,
+    len: usize,
+    last_int_value: Option<u32>,
+    /// The span of the last name or property value
+    last_string_value: Option<(usize, usize)>,
+    last_assert_is_quant: bool,
+    num_capturing_parens: u32,
+    max_back_refs: u32,
+    /// The spans of the names of named groups and the position of their `(`
+    group_names: Vec<((usize, usize), usize)>,
+    /// The spans of the names of named back references and the position of their `\`
+    back_ref_names: Vec<((usize, usize), usize)>,
+    back_refs: Vec<BackRefSpan>,
+    groups: Vec<GroupSpan>,
+    last_quantifier_bounds: (u32, Option<u32>),
+    quantifiers: Vec<QuantifierInfo>,
+    uses_lookaround: bool,
+    classes: Vec<ClassInfo>,
+    escapes: Vec<EscapeInfo>,
+    tokens: Vec<Token>,
+    n: bool,
+    /// Set when something was parsed that would mean something
+    /// else with `n` set (a `\k`)
+    n_dependent: bool,
+    u: bool,
+    /// Disables the Annex B extensions, this is checked in
+    /// `eat_term` (`eat_extended_atom`, `eat_invalid_braced_quantifier`
+    /// and quantified lookaheads), `eat_braced_quantifier`,
+    /// `eat_character_escape` (`eat_legacy_octal_escape_sequence`),
+    /// `eat_identity_escape`, `eat_back_ref`, `eat_atom_escape`,
+    /// `eat_class_atom` and `class_ranges`
+    strict: bool,
+    require_named_groups: bool,
+    operations: u64,
+    max_operations: Option<u64>,
+    max_pattern_len: Option<usize>,
+    ecma_version: EcmaVersion,
+    collect_flag_errors: bool,
+    unicode_version: Option<UnicodeVersion>,
+    collect_warnings: bool,
+    warnings: Vec<Warning>,
+    declared_names: Vec<String>,
+    warn_legacy_octal: bool,
+    warn_unbounded_lookbehind: bool,
+    /// The number of look behind assertions around the position
+    lookbehind_depth: u32,
+    /// The number of groups and classes around the position
+    depth: usize,
+    max_depth: usize,
+    case_insensitive: bool,
+    /// If the `v` flag is set
+    v: bool,
+}
+
+impl State {
+    pub fn new(len: usize, u: bool, options: &ParserOptions) -> Self {
+        Self {
+            pos: 0,
+            len,
+            last_int_value: None,
+            last_string_value: None,
+            last_assert_is_quant: false,
+            num_capturing_parens: 0,
+            max_back_refs: 0,
+            group_names: Vec::new(),
+            back_ref_names: Vec::new(),
+            back_refs: Vec::new(),
+            groups: Vec::new(),
+            last_quantifier_bounds: (0, None),
+            quantifiers: Vec::new(),
+            uses_lookaround: false,
+            classes: Vec::new(),
+            escapes: Vec::new(),
+            tokens: Vec::new(),
+            n: u || options.strict || !options.declared_names.is_empty(),
+            n_dependent: false,
+            u,
+            strict: options.strict,
+            require_named_groups: options.require_named_groups,
+            operations: 0,
+            max_operations: options.max_operations,
+            max_pattern_len: options.max_pattern_len,
+            ecma_version: options.ecma_version,
+            collect_flag_errors: options.collect_flag_errors,
+            unicode_version: options.unicode_version,
+            collect_warnings: options.collect_warnings,
+            warnings: Vec::new(),
+            declared_names: options.declared_names.clone(),
+            warn_legacy_octal: options.warn_legacy_octal,
+            warn_unbounded_lookbehind: options.warn_unbounded_lookbehind,
+            lookbehind_depth: 0,
+            depth: 0,
+            max_depth: options.max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+            case_insensitive: false,
+            v: false,
+        }
+    }
+    /// The text of `last_string_value`, `pattern` should be
+    /// the pattern that is being parsed
+    fn last_string<'b>(&self, pattern: &'b str) -> Option<&'b str> {
+        self.last_string_value
+            .and_then(|(start, end)| pattern.get(start..end))
+    }
+    /// If `\k` is a named reference before any named groups
+    /// have been found
+    fn named_from_start(&self) -> bool {
+        self.u || self.strict || !self.declared_names.is_empty()
+    }
+    pub fn reset(&mut self) {
+        self.pos = 0;
+        self.last_int_value = None;
+        self.last_string_value = None;
+        self.num_capturing_parens = 0;
+        self.max_back_refs = 0;
+        self.n_dependent = false;
+        self.group_names.clear();
+        self.back_ref_names.clear();
+        self.back_refs.clear();
+        self.groups.clear();
+        self.lookbehind_depth = 0;
+        self.depth = 0;
+        self.quantifiers.clear();
+        self.uses_lookaround = false;
+        self.classes.clear();
+        self.escapes.clear();
+        self.tokens.clear();
+        self.warnings.clear();
+    }
+}
+
+/// Validate a regular expression literal (`/pattern/flags`),
+/// this is the same as `RegexParser::new` followed by
+/// `RegexParser::validate`
+///
+/// ```
+/// assert!(res_regex::validate("/abc/g").is_ok());
+/// assert!(res_regex::validate("/a)/").is_err());
+/// assert!(res_regex::validate("/a/gg").is_err());
+/// ```
+pub fn validate(js: &str) -> Result<(), Error> {
+    RegexParser::new(js)?.validate()
+}
+
+/// Validate a pattern and flags that have already been
+/// separated, this is the same as `RegexParser::from_parts`
+/// followed by `RegexParser::validate`
+///
+/// ```
+/// assert!(res_regex::validate_parts("a/b", "gu").is_ok());
+/// assert!(res_regex::validate_parts(r"\1", "u").is_err());
+/// ```
+pub fn validate_parts(pattern: &str, flags: &str) -> Result<(), Error> {
+    RegexParser::from_parts(pattern, flags)?.validate()
+}
+
+/// Validate a regular expression literal with the Annex B
+/// extensions disabled. If that fails, the second value
+/// will be `true` when the literal would be accepted by
+/// a web browser (with Annex B enabled)
+///
+/// ```
+/// let (strict, web) = res_regex::validate_with_fallback(r"/\1/");
+/// assert!(strict.is_err());
+/// assert!(web);
+/// ```
+pub fn validate_with_fallback(js: &str) -> (Result<(), Error>, bool) {
+    let strict = RegexParser::with_options(
+        js,
+        ParserOptions {
+            strict: true,
+            ..ParserOptions::default()
+        },
+    )
+    .and_then(|mut parser| parser.validate());
+    if strict.is_ok() {
+        return (strict, false);
+    }
+    let web = RegexParser::new(js)
+        .and_then(|mut parser| parser.validate())
+        .is_ok();
+    (strict, web)
+}
+
+/// Compare two flag strings, since the order of the flags
+/// doesn't matter `"gi"` and `"ig"` are equivalent. An invalid
+/// or duplicate flag in either string is an error
+///
+/// ```
+/// assert!(res_regex::flags_equivalent("gi", "ig").unwrap());
+/// assert!(!res_regex::flags_equivalent("g", "gi").unwrap());
+/// ```
+pub fn flags_equivalent(a: &str, b: &str) -> Result<bool, Error> {
+    Ok(RegExFlags::parse(a)? == RegExFlags::parse(b)?)
+}
+
+/// Validate a single character class (including the brackets)
+/// on its own, `unicode` enables the rules for the `u` flag.
+/// Anything after the closing `]` is an error
+///
+/// ```
+/// assert!(res_regex::validate_class("[a-z]", false).is_ok());
+/// assert!(res_regex::validate_class(r"[\p{L}]", true).is_ok());
+/// assert!(res_regex::validate_class("[a-z", false).is_err());
+/// ```
+pub fn validate_class(class_src: &str, unicode: bool) -> Result<(), Error> {
+    let flags = RegExFlags {
+        unicode,
+        ..RegExFlags::default()
+    };
+    let mut parser = RegexParser::from_pattern(class_src, &flags, ParserOptions::default())?;
+    let found = parser
+        .eat_character_class()
+        .map_err(|e| e.locate(class_src))?;
+    if !found {
+        return Err(Error::new(
+            0,
+            ErrorKind::InvalidCharacterClass,
+            "Expected a character class",
+        ));
+    }
+    if parser.state.pos != parser.state.len {
+        return Err(Error::new(
+            parser.state.pos,
+            ErrorKind::TrailingCharacters,
+            "Unexpected input after character class",
+        )
+        .locate(class_src));
+    }
+    Ok(())
+}
+
+/// Every flag character that may follow a regular expression
+pub const VALID_FLAGS: &[char] = &['d', 'g', 'i', 'm', 's', 'u', 'v', 'y'];
+
+/// A short description of a flag, `None` if `c` isn't
+/// one of the `VALID_FLAGS`
+///
+/// ```
+/// assert_eq!(res_regex::flag_description('g'), Some("global"));
+/// assert_eq!(res_regex::flag_description('x'), None);
+/// ```
+pub fn flag_description(c: char) -> Option<&'static str> {
+    Some(match c {
+        'd' => "has indices",
+        'g' => "global",
+        'i' => "ignore case",
+        'm' => "multiline",
+        's' => "dot all",
+        'u' => "unicode",
+        'v' => "unicode sets",
+        'y' => "sticky",
+        _ => return None,
+    })
+}
+
+/// Parse a string of flags in a `const` context, this
+/// will panic (a compile error when used in a `const`
+/// binding) if a flag is invalid or repeated
+///
+/// ```
+/// use res_regex::{parse_flags_const, RegExFlags};
+/// const FLAGS: RegExFlags = parse_flags_const("gi");
+/// assert!(FLAGS.global() && FLAGS.case_insensitive());
+/// ```
+///
+/// ```compile_fail
+/// use res_regex::{parse_flags_const, RegExFlags};
+/// const FLAGS: RegExFlags = parse_flags_const("gx");
+/// ```
+pub const fn parse_flags_const(flags: &str) -> RegExFlags {
+    let bytes = flags.as_bytes();
+    // one bit per flag, in the order of `VALID_FLAGS`
+    let mut seen = 0u8;
+    let mut i = 0;
+    while i < bytes.len() {
+        let bit = match bytes[i] {
+            b'd' => 0,
+            b'g' => 1,
+            b'i' => 2,
+            b'm' => 3,
+            b's' => 4,
+            b'u' => 5,
+            b'v' => 6,
+            b'y' => 7,
+            _ => panic!("invalid flag"),
+        };
+        if seen & (1 << bit) != 0 {
+            panic!("duplicate flag");
+        }
+        seen |= 1 << bit;
+        i += 1;
+    }
+    let ret = RegExFlags {
+        has_indices: seen & 1 != 0,
+        global: seen & (1 << 1) != 0,
+        case_insensitive: seen & (1 << 2) != 0,
+        multi_line: seen & (1 << 3) != 0,
+        dot_matches_new_line: seen & (1 << 4) != 0,
+        unicode: seen & (1 << 5) != 0,
+        unicode_sets: seen & (1 << 6) != 0,
+        sticky: seen & (1 << 7) != 0,
+    };
+    if ret.unicode && ret.unicode_sets {
+        panic!("u and v flags are mutually exclusive");
+    }
+    ret
+}
+
+/// The flags of a regular expression, each flag is read
+/// with the method of the same name. The fields are kept
+/// private so a value can't have both `u` and `v` set
+///
+/// With the `serde` feature these are written as the flag
+/// text (like `"gi"`) which is parsed when they are read
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct RegExFlags {
+    case_insensitive: bool,
+    multi_line: bool,
+    dot_matches_new_line: bool,
+    unicode: bool,
+    global: bool,
+    sticky: bool,
+    has_indices: bool,
+    unicode_sets: bool,
+}
+
+#[cfg(feature = "serde")]
+impl From<RegExFlags> for String {
+    fn from(flags: RegExFlags) -> Self {
+        [
+            (flags.has_indices, 'd'),
+            (flags.global, 'g'),
+            (flags.case_insensitive, 'i'),
+            (flags.multi_line, 'm'),
+            (flags.dot_matches_new_line, 's'),
+            (flags.unicode, 'u'),
+            (flags.unicode_sets, 'v'),
+            (flags.sticky, 'y'),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, flag)| flag)
+        .collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl core::convert::TryFrom<String> for RegExFlags {
+    type Error = Error;
+
+    fn try_from(flags: String) -> Result<Self, Error> {
+        Self::parse(&flags)
+    }
+}
+
+impl RegExFlags {
+    /// `g`
+    pub const fn global(&self) -> bool {
+        self.global
+    }
+    /// `i`
+    pub const fn case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+    /// `m`
+    pub const fn multi_line(&self) -> bool {
+        self.multi_line
+    }
+    /// `s`
+    pub const fn dot_matches_new_line(&self) -> bool {
+        self.dot_matches_new_line
+    }
+    /// `u`
+    pub const fn unicode(&self) -> bool {
+        self.unicode
+    }
+    /// `v`
+    pub const fn unicode_sets(&self) -> bool {
+        self.unicode_sets
+    }
+    /// `y`
+    pub const fn sticky(&self) -> bool {
+        self.sticky
+    }
+    /// `d`
+    pub const fn has_indices(&self) -> bool {
+        self.has_indices
+    }
+
+    fn parse(flags: &str) -> Result<Self, Error> {
+        Self::parse_with(flags, &ParserOptions::default())
+    }
+    /// Parse a string of flags on its own, honoring the
+    /// `ecma_version` and `collect_flag_errors` options
+    fn parse_with(flags: &str, options: &ParserOptions) -> Result<Self, Error> {
+        Self::parse_at(flags, 0, options).map_err(|e| e.in_space(IndexSpace::Flags).locate(flags))
+    }
+    /// Parse `flags` that start at `offset`, with
+    /// `collect_flag_errors` every problem is reported
+    /// in a single error
+    fn parse_at(flags: &str, offset: usize, options: &ParserOptions) -> Result<Self, Error> {
+        let mut ret = Self::default();
+        let mut errors = Vec::new();
+        for (i, c) in flags.char_indices() {
+            let added = if EcmaVersion::of_flag(c) > options.ecma_version {
+                Err(Error::new(
+                    offset + i,
+                    ErrorKind::NewerThanTarget,
+                    &format!("the {} flag requires {}", c, EcmaVersion::of_flag(c)),
+                ))
+            } else {
+                ret.add_flag(c, offset + i)
+            };
+            if let Err(e) = added {
+                if !options.collect_flag_errors {
+                    return Err(e);
+                }
+                errors.push((c, e));
+            }
+        }
+        if errors.len() > 1 {
+            return Err(Self::combine_errors(errors));
+        }
+        match errors.pop() {
+            Some((_, e)) => Err(e),
+            None => Ok(ret),
+        }
+    }
+    /// Combine the errors for several flags into one, with
+    /// the position and kind of the first
+    fn combine_errors(errors: Vec<(char, Error)>) -> Error {
+        let flags_of = |kind: ErrorKind| {
+            let mut flags: Vec<String> = Vec::new();
+            for (c, e) in &errors {
+                let flag = match kind {
+                    ErrorKind::InvalidFlag => format!("{:?}", c),
+                    _ => c.to_string(),
+                };
+                if e.kind == kind && !flags.contains(&flag) {
+                    flags.push(flag);
+                }
+            }
+            flags.join(", ")
+        };
+        let mut parts = Vec::new();
+        let duplicates = flags_of(ErrorKind::DuplicateFlag);
+        if !duplicates.is_empty() {
+            parts.push(format!("duplicate flags: {}", duplicates));
+        }
+        let invalid = flags_of(ErrorKind::InvalidFlag);
+        if !invalid.is_empty() {
+            parts.push(format!("invalid flags: {}", invalid));
+        }
+        for (_, e) in &errors {
+            let listed = e.kind == ErrorKind::DuplicateFlag || e.kind == ErrorKind::InvalidFlag;
+            if !listed && !parts.contains(&e.msg) {
+                parts.push(e.msg.clone());
+            }
+        }
+        let (_, first) = &errors[0];
+        Error::new(first.idx, first.kind, &parts.join("; "))
+    }
+    fn add_flag(&mut self, c: char, pos: usize) -> Result<(), Error> {
+        match c {
+            'g' => {
+                if self.global {
+                    Err(Error::new(
+                        pos,
+                        ErrorKind::DuplicateFlag,
+                        "duplicate g flag",
+                    ))
+                } else {
+                    self.global = true;
+                    Ok(())
+                }
+            }
+            'i' => {
+                if self.case_insensitive {
+                    Err(Error::new(
+                        pos,
+                        ErrorKind::DuplicateFlag,
+                        "duplicate i flag",
+                    ))
+                } else {
+                    self.case_insensitive = true;
+                    Ok(())
+                }
+            }
+            'm' => {
+                if self.multi_line {
+                    Err(Error::new(
+                        pos,
+                        ErrorKind::DuplicateFlag,
+                        "duplicate m flag",
+                    ))
+                } else {
+                    self.multi_line = true;
+                    Ok(())
+                }
+            }
+            's' => {
+                if self.dot_matches_new_line {
+                    Err(Error::new(
+                        pos,
+                        ErrorKind::DuplicateFlag,
+                        "duplicate s flag",
+                    ))
+                } else {
+                    self.dot_matches_new_line = true;
+                    Ok(())
+                }
+            }
+            'u' => {
+                if self.unicode {
+                    Err(Error::new(
+                        pos,
+                        ErrorKind::DuplicateFlag,
+                        "duplicate u flag",
+                    ))
+                } else if self.unicode_sets {
+                    Err(Error::new(
+                        pos,
+                        ErrorKind::IncompatibleFlags,
+                        "u and v flags are mutually exclusive",
+                    ))
+                } else {
+                    self.unicode = true;
+                    Ok(())
+                }
+            }
+            'y' => {
+                if self.sticky {
+                    Err(Error::new(
+                        pos,
+                        ErrorKind::DuplicateFlag,
+                        "duplicate y flag",
+                    ))
+                } else {
+                    self.sticky = true;
+                    Ok(())
+                }
+            }
+            'd' => {
+                if self.has_indices {
+                    Err(Error::new(
+                        pos,
+                        ErrorKind::DuplicateFlag,
+                        "duplicate d flag",
+                    ))
+                } else {
+                    self.has_indices = true;
+                    Ok(())
+                }
+            }
+            'v' => {
+                if self.unicode_sets {
+                    Err(Error::new(
+                        pos,
+                        ErrorKind::DuplicateFlag,
+                        "duplicate v flag",
+                    ))
+                } else if self.unicode {
+                    Err(Error::new(
+                        pos,
+                        ErrorKind::IncompatibleFlags,
+                        "u and v flags are mutually exclusive",
+                    ))
+                } else {
+                    self.unicode_sets = true;
+                    Ok(())
+                }
+            }
+            _ => Err(Error::new(
+                pos,
+                ErrorKind::InvalidFlag,
+                &format!("invalid flag {:?}", c),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn lots_of_regexes() {
+        run_test("/asdf|fdsa/g").unwrap();
+    }
+    #[test]
+    #[should_panic = "Invalid escape"]
+    fn decimal_escape_with_u() {
+        run_test(r"/\1/u").unwrap()
+    }
+
+    #[test]
+    #[should_panic = "invalid flag"]
+    fn invalid_regex_flag() {
+        run_test("/./G").unwrap();
+    }
+
+    #[test]
+    #[should_panic = "Nothing to repeat"]
+    fn bad_look_behind() {
+        run_test(r"/.(?<=.)?/").unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn bad_quant() {
+        run_test(r"/{2}/").unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn id_continue_u() {
+        run_test(r"/\M/u").unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn cant_start_with_star() {
+        run_test("/*/").unwrap();
+    }
+
+    #[test]
+    fn unicode_name_and_value() {
+        for value in unicode_tables::general_category::GC {
+            run_test(&format!(r"/\p{{General_Category={}}}/u", value))
+                .unwrap_or_else(|_| panic!("failed at General_category={}", value));
+            run_test(&format!(r"/\p{{gc={}}}/u", value))
+                .unwrap_or_else(|_| panic!("failed at gc={}", value));
+        }
+        for value in unicode_tables::script_values::SCRIPT {
+            run_test(&format!(r"/\p{{Script={}}}/u", value))
+                .unwrap_or_else(|_| panic!("failed at Script={}", value));
+            run_test(&format!(r"/\p{{sc={}}}/u", value))
+                .unwrap_or_else(|_| panic!("failed at sc={}", value));
+            run_test(&format!(r"/\p{{Script_Extensions={}}}/u", value))
+                .unwrap_or_else(|_| panic!("failed at Script_Extensions={}", value));
+            run_test(&format!(r"/\p{{scx={}}}/u", value))
+                .unwrap_or_else(|_| panic!("failed at scx={}", value));
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn unicode_name_and_value_bad_name() {
+        run_test(r"/\p{junk=Greek}/u").unwrap();
+    }
+    #[test]
+    #[should_panic]
+    fn unicode_name_and_value_bad_value() {
+        run_test(r"/\p{General_Category=Geek}/u").unwrap();
+    }
+    #[test]
+    #[should_panic]
+    fn unicode_name_or_value_bad_value() {
+        run_test(r"/\p{junk}/u").unwrap();
+    }
+    #[test]
+    fn unicode_name_or_value() {
+        for value in unicode_tables::GC_AND_BP {
+            run_test(&format!(r"/\p{{{}}}/u", value)).unwrap();
+        }
+    }
+
+    #[test]
+    fn named_group() {
+        run_test(r"/(?<x>a)|b/").unwrap();
+    }
+
+    #[test]
+    fn class_escape_range_u() {
+        let err = run_test(r"/[\d-\s]/u").unwrap_err();
+        assert_eq!(err.msg, "Invalid character class");
+        assert_eq!(err.idx, 3);
+    }
+
+    #[test]
+    fn class_escape_range() {
+        run_test(r"/[\d-\s]/").unwrap();
+    }
+
+    #[test]
+    fn quantifier_info() {
+        let mut parser = RegexParser::new("/a+b{2,3}?/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(
+            parser.quantifiers(),
+            vec![
+                QuantifierInfo {
+                    at: 1,
+                    min: 1,
+                    max: None,
+                    greedy: true,
+                    target_span: (0, 1),
+                    span: (1, 2),
+                },
+                QuantifierInfo {
+                    at: 3,
+                    min: 2,
+                    max: Some(3),
+                    greedy: false,
+                    target_span: (2, 3),
+                    span: (3, 9),
+                },
+            ]
+        );
+        let mut parser = RegexParser::new("/(a)*?b?c{0,1}[d]{22}/").unwrap();
+        parser.validate().unwrap();
+        let spans: Vec<_> = parser.quantifiers().iter().map(|q| q.span).collect();
+        assert_eq!(spans, vec![(3, 5), (6, 7), (8, 13), (16, 20)]);
+        // spans after an edit are moved by a region revalidation
+        let mut parser = RegexParser::new("/(?:a)b{2}/").unwrap();
+        parser.validate().unwrap();
+        parser.revalidate_region("(?:abc)b{2}", 4).unwrap();
+        assert_eq!(parser.quantifiers()[0].span, (8, 11));
+    }
+
+    #[test]
+    fn fallback_to_annex_b() {
+        let (strict, web) = validate_with_fallback(r"/\1/");
+        assert!(strict.is_err());
+        assert!(web);
+        let (strict, web) = validate_with_fallback(r"/(a)\1/");
+        assert!(strict.is_ok());
+        assert!(!web);
+        let (strict, web) = validate_with_fallback(r"/(/");
+        assert!(strict.is_err());
+        assert!(!web);
+    }
+
+    #[test]
+    fn strict_rejects_annex_b() {
+        for re in &[
+            r"/\1/",
+            r"/\01/",
+            r"/]/",
+            r"/a{/",
+            r"/(?=a)*/",
+            r"/\a/",
+            r"/[\a]/",
+            r"/[\00]/",
+            r"/[\01]/",
+            r"/[\1]/",
+            r"/[\c]/",
+            r"/[\c1]/",
+            r"/[\k]/",
+            r"/[\d-a]/",
+            r"/[a-\w]/",
+        ] {
+            run_strict_test(re).unwrap_err();
+            run_test(re).unwrap();
+        }
+        run_strict_test(r"/(a)\1\./").unwrap();
+        for re in &[r"/[\0\b\-\.\ca]/", r"/[\d\-a]/", r"/[a-z\d]/"] {
+            run_strict_test(re).unwrap_or_else(|e| panic!("{} {}", re, e));
+        }
+    }
+
+    #[test]
+    fn require_named_groups() {
+        let options = ParserOptions {
+            require_named_groups: true,
+            ..ParserOptions::default()
+        };
+        for re in &[
+            "/(?<x>a)/",
+            "/(?:a)/",
+            "/(?=a)(?<!b)/",
+            r"/(?<x>(?:a)|\(b\))\k<x>/",
+        ] {
+            let mut parser = RegexParser::with_options(re, options.clone()).unwrap();
+            parser.validate().unwrap();
+        }
+        for (re, idx) in &[("/(a)/", 0), ("/(?<x>b(a))/", 6), ("/[(]|(a)/", 4)] {
+            let mut parser = RegexParser::with_options(re, options.clone()).unwrap();
+            let err = parser.validate().unwrap_err();
+            assert_eq!(err.idx, *idx, "{}", re);
+            assert_eq!(
+                err.msg,
+                "Anonymous capturing group; use (?<name>...) or (?:...)"
+            );
+            run_test(re).unwrap();
+        }
+    }
+
+    #[test]
+    fn strict_named_references() {
+        for re in &[r"/(?<a>x)\k<a>/", r"/\k<a>(?<a>x)/"] {
+            run_test(re).unwrap_or_else(|e| panic!("{} {}", re, e));
+            run_strict_test(re).unwrap_or_else(|e| panic!("{} {}", re, e));
+        }
+        for re in &[r"/\k/", r"/\k<a>/", r"/(?<a>x)\k<b>/"] {
+            run_strict_test(re).unwrap_err();
+        }
+        let mut parser = RegexParser::with_strict("/a/", true).unwrap();
+        parser.validate().unwrap();
+        assert!(parser.is_named_mode());
+        assert!(!RegexParser::with_strict("/a/", false)
+            .unwrap()
+            .is_named_mode());
+    }
+
+    #[test]
+    fn linear_safe() {
+        for (re, expected) in &[
+            (r"/(a)\1/", false),
+            (r"/(a)\1/u", false),
+            (r"/(?<x>a)\k<x>/", false),
+            (r"/(?=a)b/", false),
+            (r"/(?<a>x)(?=y)*/", false),
+            (r"/(?<a>x)(?=y){2}/", false),
+            (r"/(?<!a)b/", false),
+            (r"/a+b*/", true),
+            (r"/(a)|b/", true),
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(parser.is_linear_safe(), *expected, "{}", re);
+        }
+    }
+
+    #[test]
+    fn max_pattern_len() {
+        let options = ParserOptions {
+            max_pattern_len: Some(100),
+            ..ParserOptions::default()
+        };
+        let long = format!("/{}/", "a".repeat(200));
+        let err = RegexParser::with_options(&long, options.clone())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind, ErrorKind::PatternTooLong);
+        assert_eq!(err.idx, 100);
+        assert_eq!(err.msg, "Pattern too long (200 bytes, the limit is 100)");
+        // `é` is two bytes, the limit falls inside the third one
+        let options = ParserOptions {
+            max_pattern_len: Some(5),
+            ..ParserOptions::default()
+        };
+        let err = RegexParser::with_options("/ééé/", options.clone())
+            .err()
+            .unwrap();
+        assert_eq!(err.idx, 4);
+        let mut parser = RegexParser::with_options("/éé/", options.clone()).unwrap();
+        let err = parser.reset_with("ééé", "").unwrap_err();
+        assert_eq!(err.idx, 4);
+        let options = ParserOptions {
+            max_pattern_len: Some(100),
+            ..ParserOptions::default()
+        };
+        let short = format!("/{}/", "a".repeat(50));
+        RegexParser::with_options(&short, options)
+            .unwrap()
+            .validate()
+            .unwrap();
+    }
+
+    #[test]
+    fn max_operations() {
+        let re = r"/(?:a|b)*c[d-f]+(?<x>\d{2,3})\k<x>/";
+        let options = ParserOptions {
+            max_operations: Some(10),
+            ..ParserOptions::default()
+        };
+        let err = RegexParser::with_options(re, options)
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.msg, "Parse budget exceeded");
+        let options = ParserOptions {
+            max_operations: Some(1_000_000),
+            ..ParserOptions::default()
+        };
+        RegexParser::with_options(re, options)
+            .unwrap()
+            .validate()
+            .unwrap();
+        // a single class is charged as it is parsed
+        let class = "a".repeat(1000);
+        let set = format!("[a]{}", "&&[a]".repeat(500));
+        for re in &[
+            format!("/[{}]/", class),
+            format!("/[{}]/v", class),
+            format!("/[{}]/v", set),
+        ] {
+            let options = ParserOptions {
+                max_operations: Some(100),
+                ..ParserOptions::default()
+            };
+            let err = RegexParser::with_options(re, options)
+                .unwrap()
+                .validate()
+                .unwrap_err();
+            assert_eq!(err.kind, ErrorKind::BudgetExceeded, "{}", re);
+            run_test(re).unwrap();
+        }
+    }
+
+    #[test]
+    fn empty_alternatives() {
+        for re in &[
+            r"/(|)/", r"/(?:|)/", r"/(a|)/", r"/(|a)/", r"/(|)+/", r"/(|)/u",
+        ] {
+            run_test(re).unwrap_or_else(|e| panic!("{} failed: {}", re, e));
+        }
+    }
+
+    #[test]
+    fn class_char_set() {
+        let mut parser = RegexParser::new("/[a-cxy]/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(
+            parser.class_as_char_set((0, 7)),
+            Some(vec![
+                ('a' as u32, 'c' as u32),
+                ('x' as u32, 'x' as u32),
+                ('y' as u32, 'y' as u32)
+            ])
+        );
+        let mut parser = RegexParser::new(r"/[a-][\w][^a]/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(
+            parser.class_as_char_set((0, 4)),
+            Some(vec![('a' as u32, 'a' as u32), (0x2D, 0x2D)])
+        );
+        assert_eq!(parser.class_as_char_set((4, 8)), None);
+        assert_eq!(parser.class_as_char_set((8, 12)), None);
+    }
+
+    #[test]
+    fn unicode_version() {
+        run_test(r"/\p{Script=Chorasmian}/u").unwrap();
+        run_test(r"/\p{scx=Kawi}/u").unwrap();
+        let options = ParserOptions {
+            unicode_version: Some(UnicodeVersion::new(12, 1)),
+            ..ParserOptions::default()
+        };
+        RegexParser::with_options(r"/\p{Script=Chorasmian}/u", options.clone())
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        RegexParser::with_options(r"/\p{Script=Greek}/u", options)
+            .unwrap()
+            .validate()
+            .unwrap();
+    }
+
+    #[test]
+    fn dot_in_class_warning() {
+        let warnings = run_warnings_test("/[a.b]/").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].idx, 2);
+        assert!(warnings[0].msg.contains("literal dot"));
+        assert!(run_warnings_test("/a.b/").unwrap().is_empty());
+        assert!(run_warnings_test(r"/[a\.b]/").unwrap().is_empty());
+        let mut parser = RegexParser::new("/[a.b]/").unwrap();
+        parser.validate().unwrap();
+        assert!(parser.warnings().is_empty());
+    }
+
+    #[test]
+    fn property_escape_without_u() {
+        let mut parser = RegexParser::new(r"/[\p{L}]/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(
+            parser.class_as_char_set((0, 7)),
+            Some(vec![(0x70, 0x70), (0x7B, 0x7B), (0x4C, 0x4C), (0x7D, 0x7D)])
+        );
+        // the `}` before the dash makes this an out of order range
+        let err = run_test(r"/[\p{L}-z]/").unwrap_err();
+        assert!(err.msg.contains("out of order"));
+        run_test(r"/[\p{L}-~]/").unwrap();
+        let warnings = run_warnings_test(r"/[\p{L}]\P/").unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].idx, 1);
+        assert_eq!(warnings[1].idx, 7);
+        assert!(run_warnings_test(r"/[\p{L}]/u").unwrap().is_empty());
+    }
+
+    #[test]
+    fn case_folding_warning() {
+        let warnings = run_warnings_test("/[a-z]/iu").unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].idx, 1);
+        assert!(warnings[0].msg.contains("case folding"));
+        assert_eq!(run_warnings_test(r"/x[\x41-\x5A]/iu").unwrap()[0].idx, 2);
+        for re in &["/[a-z]/i", "/[a-z]/u", "/[a-j]/iu", "/[t-z0-9]/iu"] {
+            assert!(run_warnings_test(re).unwrap().is_empty(), "{}", re);
+        }
+    }
+
+    #[test]
+    fn sibling_back_ref_warning() {
+        for (re, idx) in &[
+            (r"/(a)|\1/", 4),
+            (r"/(?<x>a)|b\k<x>/", 9),
+            (r"/(?:(a)|b\1)/", 8),
+            (r"/\2|((a)|b)/u", 0),
+        ] {
+            let warnings = run_warnings_test(re).unwrap();
+            assert_eq!(warnings.len(), 1, "{}", re);
+            assert_eq!(warnings[0].idx, *idx, "{}", re);
+            assert!(warnings[0].msg.contains("different alternative"));
+        }
+        for re in &[
+            r"/(a)\1/",
+            r"/(a|\1)/",
+            r"/((a)|b)\2/",
+            r"/(a)(?:b|\1)/",
+            r"/\k<x>(?<x>a)/",
+        ] {
+            assert!(run_warnings_test(re).unwrap().is_empty(), "{}", re);
+        }
+    }
+
+    #[test]
+    fn detailed_validation() {
+        let mut parser = RegexParser::new("/[]/").unwrap();
+        let warnings = parser.validate_detailed().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].msg.contains("empty character class"));
+        // `collect_warnings` is left as it was
+        parser.validate().unwrap();
+        assert!(parser.warnings().is_empty());
+        let mut parser = RegexParser::new("/[^]a/").unwrap();
+        assert!(parser.validate_detailed().unwrap().is_empty());
+        let mut parser = RegexParser::new("/(/").unwrap();
+        parser.validate_detailed().unwrap_err();
+    }
+
+    #[test]
+    fn closing_slash() {
+        let cases: &[(&str, &str, &str)] = &[
+            ("/[/]/", "[/]", ""),
+            (r"/a\/b/", r"a\/b", ""),
+            (r"/\//g", r"\/", "g"),
+            (r"/[\]/]/", r"[\]/]", ""),
+            ("/[[/]/", "[[/]", ""),
+            ("/é[/]é/u", "é[/]é", "u"),
+        ];
+        for (js, pattern, flags) in cases.iter() {
+            let mut parser = RegexParser::new(js).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(&parser.pattern, pattern, "{}", js);
+            assert_eq!(*parser.flags(), RegExFlags::parse(flags).unwrap(), "{}", js);
+        }
+        // the first unescaped `/` outside of a class ends the pattern
+        let err = RegexParser::new("/a/b/").err().unwrap();
+        assert_eq!((err.kind, err.idx), (ErrorKind::InvalidFlag, 3));
+        for js in ["/[/", r"/a\/", "/"].iter() {
+            let err = RegexParser::new(js).err().unwrap();
+            assert_eq!(err.kind, ErrorKind::InvalidLiteral, "{}", js);
+        }
+    }
+
+    #[test]
+    fn escaped_slash() {
+        for re in &[r"/a\/b/", r"/a\/b/u"] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(
+                parser.escapes(),
+                &[EscapeInfo {
+                    span: (1, 3),
+                    value: Some(0x2F),
+                    back_reference: false,
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn capture_count() {
+        for (re, expected) in &[
+            ("/a/", 0),
+            ("/(a)(?:b)/", 1),
+            ("/(a(b))(?<c>c)/", 3),
+            ("/(?<=(a))b/", 1),
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(parser.capture_count(), *expected, "{}", re);
+        }
+    }
+
+    #[test]
+    fn empty_pattern() {
+        for re in &["//", "//u", "//v", "/ /", "/ /u"] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            // a second pass over the same parser should also be fine
+            parser.validate().unwrap();
+        }
+        for flags in &["", "u", "v"] {
+            let mut parser = RegexParser::from_parts("", flags).unwrap();
+            parser.validate().unwrap();
+            parser.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn first_and_last_token() {
+        for (re, first, last) in &[
+            (
+                "/^abc$/",
+                Some(TokenKind::Assertion),
+                Some(TokenKind::Assertion),
+            ),
+            (
+                "/abc+/",
+                Some(TokenKind::Literal),
+                Some(TokenKind::Quantifier),
+            ),
+            (
+                r"/\d|[a-z]/u",
+                Some(TokenKind::Escape),
+                Some(TokenKind::ClassClose),
+            ),
+            ("/(?:a)./", Some(TokenKind::GroupOpen), Some(TokenKind::Dot)),
+            (
+                "/(?<x>a)/",
+                Some(TokenKind::GroupOpen),
+                Some(TokenKind::GroupClose),
+            ),
+            ("//", None, None),
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(parser.first_token(), *first, "{}", re);
+            assert_eq!(parser.last_token(), *last, "{}", re);
+        }
+    }
+
+    #[test]
+    fn look_behind_captures() {
+        for re in &[r"/(?<=(a))\1/", r"/(?<=(a))\1/u", r"/(?<!(a))\1/"] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(parser.capture_count(), 1, "{}", re);
+            assert_eq!(parser.max_back_reference(), 1, "{}", re);
+        }
+    }
+
+    #[test]
+    fn control_range() {
+        run_test(r"/[\t-\r]/").unwrap();
+    }
+
+    #[test]
+    fn known_flags() {
+        let flags = &['d', 'g', 'i', 'm', 's', 'u', 'y'];
+        for flag in flags {
+            run_test(&format!("/.+/{}", flag)).unwrap();
+        }
+        let all: String = flags.iter().collect();
+        run_test(&format!("/.+/{}", all)).unwrap();
+    }
+
+    #[test]
+    fn duplicate_known_flags() {
+        let flags = &['d', 'g', 'i', 'm', 's', 'u', 'y'];
+        for flag in flags {
+            run_test(&format!("/.+/{0}{}0", flag)).unwrap_err();
+        }
+    }
+    #[test]
+    fn duplicate_flag_index() {
+        for flag in &['d', 'v'] {
+            let err = run_test(&format!("/a/{0}{0}", flag)).unwrap_err();
+            assert_eq!(err.msg, format!("duplicate {} flag", flag));
+            assert_eq!(err.idx, 4);
+        }
+    }
+
+    #[test]
+    fn flag_pairs() {
+        let flags = &['d', 'g', 'i', 'm', 's', 'u', 'v', 'y'];
+        for first in flags {
+            for second in flags {
+                let re = format!("/a/{}{}", first, second);
+                let result = run_test(&re);
+                if first == second {
+                    assert!(result.is_err(), "{} should be a duplicate", re);
+                } else if (*first == 'u' && *second == 'v') || (*first == 'v' && *second == 'u') {
+                    let err = result.unwrap_err();
+                    assert_eq!(err.msg, "u and v flags are mutually exclusive");
+                    assert_eq!(err.idx, 4);
+                } else {
+                    result.unwrap_or_else(|e| panic!("{} failed: {}", re, e));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn equivalent_flags() {
+        assert!(flags_equivalent("gi", "ig").unwrap());
+        assert!(flags_equivalent("", "").unwrap());
+        assert!(flags_equivalent("dgimsuy", "yusmigd").unwrap());
+        assert!(!flags_equivalent("g", "gi").unwrap());
+        assert!(!flags_equivalent("u", "v").unwrap());
+        let err = flags_equivalent("gi", "igg").unwrap_err();
+        assert_eq!(err.idx, 2);
+        flags_equivalent("x", "g").unwrap_err();
+    }
+
+    #[test]
+    fn renumber_groups() {
+        let mut parser = RegexParser::new(r"/(a)(b)\2/").unwrap();
+        let mut pattern = parser.parse().unwrap();
+        pattern.disjunction.alternatives[0].terms.remove(0);
+        ast::renumber_backreferences(&mut pattern).unwrap();
+        assert_eq!(pattern.to_string(), r"(b)\1");
+        let mut parser = RegexParser::new(r"/(a)(b)\1/").unwrap();
+        let mut pattern = parser.parse().unwrap();
+        pattern.disjunction.alternatives[0].terms.remove(0);
+        ast::renumber_backreferences(&mut pattern).unwrap_err();
+        for re in &[
+            r"/^a.b*?c{2,}d{3}e{1,2}$/",
+            r"/(?:a|b)(?<n>c)\k<n>(?=d)(?<!e)/",
+            r"/[^a-z\d\-][\b\x41-\u{5A}]/u",
+            r"/\p{Script=Latin}\P{L}\bx\B/u",
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            let pattern = parser.parse().unwrap();
+            assert_eq!(pattern.to_string(), re[1..re.rfind('/').unwrap()]);
+        }
+    }
+
+    #[test]
+    fn regex_source() {
+        for (source, flags) in &[(r"a\/b", ""), (r"a\/b", "u"), ("a/b", "g"), ("", "")] {
+            let mut parser = RegexParser::from_regex_source(source, flags).unwrap();
+            parser.validate().unwrap();
+        }
+        let mut parser = RegexParser::from_regex_source(r"(a)\/\1", "i").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.escapes()[0].span, (3, 5));
+        assert_eq!(parser.escapes()[0].value, Some(0x2F));
+        let err = RegexParser::from_regex_source("a", "gg").err().unwrap();
+        assert_eq!(err.idx, 1);
+        let mut parser = RegexParser::from_regex_source(r"\p{L}/", "u").unwrap();
+        parser.validate().unwrap();
+    }
+
+    #[test]
+    fn duplicate_group_name() {
+        let err = run_test("/(?<x>a)(?<x>b)/").unwrap_err();
+        assert_eq!(
+            err.msg,
+            "Duplicate capture group name 'x' (first defined at 0)"
+        );
+        assert_eq!(err.idx, 12);
+        let err = run_test("/a(?<y>b)(?<x>c)(?<x>d)/u").unwrap_err();
+        assert!(err.msg.contains("'x'"));
+        assert!(err.msg.contains("first defined at 8"));
+    }
+
+    #[test]
+    fn operator_positions() {
+        let mut parser = RegexParser::new("/a(b|c)*/").unwrap();
+        assert_eq!(
+            parser.operator_positions().unwrap(),
+            vec![(1, '('), (3, '|'), (5, ')'), (6, '*')]
+        );
+        let mut parser = RegexParser::new(r"/^(?:\(|[(|)]){2,}?$/").unwrap();
+        assert_eq!(
+            parser.operator_positions().unwrap(),
+            vec![
+                (0, '^'),
+                (1, '('),
+                (2, '?'),
+                (6, '|'),
+                (7, '['),
+                (11, ']'),
+                (12, ')'),
+                (13, '{'),
+                (16, '}'),
+                (17, '?'),
+                (18, '$'),
+            ]
+        );
+        let mut parser = RegexParser::new(r"/\b\B/").unwrap();
+        assert!(parser.operator_positions().unwrap().is_empty());
+        let mut parser = RegexParser::new(r"/(?<$a>x)\k<$a>(?<=$)/").unwrap();
+        assert_eq!(
+            parser.operator_positions().unwrap(),
+            vec![
+                (0, '('),
+                (1, '?'),
+                (7, ')'),
+                (14, '('),
+                (15, '?'),
+                (18, '$'),
+                (19, ')'),
+            ]
+        );
+    }
+
+    #[test]
+    fn control_letter_values() {
+        let mut parser = RegexParser::new(r"/\cA\cJ\cZ\ca\cj\cz[\cM]/u").unwrap();
+        parser.validate().unwrap();
+        let values: Vec<Option<u32>> = parser.escapes().iter().map(|e| e.value).collect();
+        assert_eq!(
+            values,
+            vec![
+                Some(0x01),
+                Some(0x0A),
+                Some(0x1A),
+                Some(0x01),
+                Some(0x0A),
+                Some(0x1A),
+                Some(0x0D)
+            ]
+        );
+        for re in &[r"/\c@/u", r"/\c1/u", r"/\c_/u", r"/[\c@]/u"] {
+            run_test(re).unwrap_err();
+        }
+        // without `u` an invalid control letter leaves the `\` as a literal
+        let mut parser = RegexParser::new(r"/\c@/").unwrap();
+        parser.validate().unwrap();
+        assert!(parser.escapes().is_empty());
+    }
+
+    #[test]
+    fn class_in_isolation() {
+        validate_class("[a-z]", false).unwrap();
+        validate_class("[^]", false).unwrap();
+        validate_class(r"[\d-a]", false).unwrap();
+        let err = validate_class("[a-z", false).unwrap_err();
+        assert!(err.msg.contains("Unterminated"));
+        let err = validate_class("[a]b", false).unwrap_err();
+        assert_eq!(err.idx, 3);
+        assert_eq!(validate_class("a", false).unwrap_err().idx, 0);
+        assert_eq!(validate_class("", false).unwrap_err().idx, 0);
+        validate_class(r"[\d-a]", true).unwrap_err();
+        validate_class("[z-a]", true).unwrap_err();
+    }
+
+    #[test]
+    fn declared_names() {
+        let options = ParserOptions {
+            declared_names: vec!["late".to_string()],
+            ..ParserOptions::default()
+        };
+        let mut parser = RegexParser::with_options(r"/\k<late>abc/", options.clone()).unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.escapes()[0].span, (0, 8));
+        let mut parser = RegexParser::with_options(r"/\k<other>abc/", options).unwrap();
+        parser.validate().unwrap_err();
+        run_test(r"/\k<late>(?<late>x)/").unwrap();
+        run_test(r"/\k<late>(?<late>x)/u").unwrap();
+    }
+
+    #[test]
+    fn unicode_and_named_modes() {
+        let mut parser = RegexParser::new("/(?<a>x)/").unwrap();
+        parser.validate().unwrap();
+        assert!(parser.is_named_mode());
+        assert!(!parser.is_unicode_mode());
+        let mut parser = RegexParser::new(r"/\k<a>/").unwrap();
+        parser.validate().unwrap();
+        assert!(!parser.is_named_mode());
+        assert!(!parser.is_unicode_mode());
+        let mut parser = RegexParser::new("/x/u").unwrap();
+        parser.validate().unwrap();
+        assert!(parser.is_named_mode());
+        assert!(parser.is_unicode_mode());
+    }
+
+    #[test]
+    fn empty_group_name() {
+        for re in &["/(?<>a)/", "/(?<>a)/u"] {
+            let err = run_test(re).unwrap_err();
+            assert_eq!(err.msg, "Empty capture group name", "{}", re);
+        }
+        let err = run_test("/(?<>a)/").unwrap_err();
+        assert_eq!(err.idx, 3);
+    }
+
+    #[test]
+    fn named_references() {
+        let mut parser = RegexParser::new(r"/(?<x>a)\k<x>b\k<x>/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.references_to("x"), vec![7, 13]);
+        assert!(parser.references_to("y").is_empty());
+        let mut parser = RegexParser::new(r"/\k<y>(?<x>a)(?<y>b)\k<x>/u").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.references_to("y"), vec![0]);
+        assert_eq!(parser.references_to("x"), vec![19]);
+    }
+
+    #[test]
+    fn empty_unicode_braces() {
+        let err = run_test(r"/\u{}/u").unwrap_err();
+        assert_eq!(err.msg, "Empty unicode escape");
+        assert_eq!(err.idx, 2);
+        let err = run_test(r"/[\u{}]/u").unwrap_err();
+        assert_eq!(err.idx, 3);
+        for re in &[r"/\u{ }/u", r"/\u{/u", r"/\u{g}/u"] {
+            let err = run_test(re).unwrap_err();
+            assert_eq!(err.idx, 2, "{}", re);
+            assert!(err.msg.contains("expected hex digits"), "{}", re);
+        }
+        let err = run_test(r"/\u{41/u").unwrap_err();
+        assert_eq!(err.msg, "Invalid unicode escape");
+        // without `u` the braces are literal characters
+        run_test(r"/\u{}/").unwrap();
+    }
+
+    #[test]
+    fn describe() {
+        let mut parser = RegexParser::new("/a+/").unwrap();
+        let description = parser.describe().unwrap();
+        assert!(description.contains("one or more"));
+        assert_eq!(description, "/a+/ means: one or more 'a'");
+        let mut parser = RegexParser::new(r"/[^a-c\d]{2,}?(?=x)\1?/").unwrap();
+        assert_eq!(
+            parser.describe().unwrap(),
+            "/[^a-c\\d]{2,}?(?=x)\\1?/ means: at least 2 of none of 'a' to 'c', a digit \
+             (as few as possible), then followed by ('x'), then optionally U+0001"
+        );
+        let mut parser = RegexParser::new(r"/(?<y>\s)|\k<y>|/").unwrap();
+        assert_eq!(
+            parser.describe().unwrap(),
+            "/(?<y>\\s)|\\k<y>|/ means: either (group 'y' (whitespace)) or \
+             (the same text as group 'y') or (nothing)"
+        );
+    }
+
+    #[test]
+    fn nul_escape() {
+        for re in &[r"/\0/u", r"/\0/", r"/[\0]/u", r"/(a)\0/u"] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(parser.escapes()[0].value, Some(0), "{}", re);
+            assert_eq!(parser.max_back_reference(), 0, "{}", re);
+        }
+        let mut parser = RegexParser::new(r"/\01/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.escapes()[0].value, Some(1));
+        run_test(r"/\01/u").unwrap_err();
+        run_test(r"/(a)\01/u").unwrap_err();
+        run_strict_test(r"/\01/").unwrap_err();
+    }
+
+    #[test]
+    fn error_coordinates() {
+        // construction errors are relative to the whole literal
+        let js = "/abc/gig";
+        let err = RegexParser::new(js).err().unwrap();
+        assert_eq!(err.idx, 7);
+        assert_eq!(&js[err.idx..], "g");
+        // validation errors are relative to the pattern
+        let js = r"/a\u{}/u";
+        let err = run_test(js).unwrap_err();
+        assert_eq!(err.idx, 3);
+        assert_eq!(&js[err.idx + 1..err.idx + 2], "{");
+    }
+
+    #[test]
+    fn error_index_space() {
+        for (js, idx) in &[("a/", 0), ("/", 0), ("/a/x", 3), ("/a/uv", 4)] {
+            let err = RegexParser::new(js).err().unwrap();
+            assert_eq!(err.space, IndexSpace::Literal, "{}", js);
+            assert_eq!(err.idx, *idx, "{}", js);
+            assert_eq!(err.literal_idx(), *idx, "{}", js);
+        }
+        let err = run_test(r"/a\u{}/u").unwrap_err();
+        assert_eq!(err.space, IndexSpace::Pattern);
+        assert_eq!(err.literal_idx(), 4);
+        let err = RegexParser::from_regex_source("a", "gx").err().unwrap();
+        assert_eq!(err.space, IndexSpace::Flags);
+        assert_eq!(err.idx, 1);
+        let err = flags_equivalent("g", "mm").unwrap_err();
+        assert_eq!(err.space, IndexSpace::Flags);
+        assert_eq!(
+            validate_class("[a", false).unwrap_err().space,
+            IndexSpace::Pattern
+        );
+    }
+
+    #[test]
+    fn body_offset() {
+        assert_eq!(RegexParser::new("/ab/").unwrap().body_offset(), 1);
+        assert_eq!(RegexParser::from_parts("ab", "").unwrap().body_offset(), 0);
+        assert_eq!(
+            RegexParser::from_regex_source("ab", "")
+                .unwrap()
+                .body_offset(),
+            0
+        );
+        let js = "/ab)/";
+        let mut parser = RegexParser::new(js).unwrap();
+        let err = parser.validate().unwrap_err();
+        assert_eq!(err.idx + parser.body_offset(), err.literal_idx());
+        let mut parser = RegexParser::from_parts("ab)", "").unwrap();
+        let err = parser.validate().unwrap_err();
+        assert_eq!(err.idx + parser.body_offset(), 3);
+    }
+
+    #[test]
+    fn revalidate_region() {
+        let mut parser = RegexParser::new(r"/(a)(?:b[c-e]\t)+\1/").unwrap();
+        parser.validate().unwrap();
+        // edit inside the class
+        parser
+            .revalidate_region(r"(a)(?:b[c-ex]\t)+\1", 10)
+            .unwrap();
+        assert_eq!(
+            parser.class_as_char_set((7, 13)),
+            Some(vec![(0x63, 0x65), (0x78, 0x78)])
+        );
+        assert_eq!(parser.escapes()[0].span, (13, 15));
+        assert_eq!(parser.quantifiers()[0].at, 16);
+        assert_eq!(parser.quantifiers()[0].target_span, (3, 16));
+        assert_eq!(parser.capture_count(), 1);
+        assert_eq!(parser.max_back_reference(), 1);
+        // edit inside the group
+        parser
+            .revalidate_region(r"(a)(?:b[c-ex]|\d)+\1", 13)
+            .unwrap();
+        assert_eq!(parser.escapes()[0].span, (14, 16));
+        assert_eq!(parser.last_token(), Some(TokenKind::Escape));
+        let full = {
+            let mut parser = RegexParser::new(r"/(a)(?:b[c-ex]|\d)+\1/").unwrap();
+            parser.validate().unwrap();
+            parser.state.tokens.clone()
+        };
+        assert_eq!(parser.state.tokens, full);
+        // an invalid edit
+        let err = parser
+            .revalidate_region(r"(a)(?:b[x-c]|\d)+\1", 8)
+            .unwrap_err();
+        assert!(err.msg.contains("out of order"));
+        // after a failure the next call is a full parse
+        parser.revalidate_region(r"(a)(?:b|(c))+\2", 7).unwrap();
+        assert_eq!(parser.capture_count(), 2);
+        // edits outside of a group are a full parse
+        parser.revalidate_region(r"x(a)(?:b|(c))+\2", 0).unwrap();
+        assert_eq!(parser.state.tokens[0].span, (0, 1));
+    }
+
+    #[test]
+    fn const_flags() {
+        const ALL: RegExFlags = parse_flags_const("dgimsvy");
+        const NONE: RegExFlags = parse_flags_const("");
+        assert_eq!(ALL, RegExFlags::parse("ydgimsv").unwrap());
+        assert_eq!(NONE, RegExFlags::default());
+        assert!(ALL.has_indices() && ALL.unicode_sets() && !ALL.unicode());
+        for flags in &["gg", "uv", "x", "é"] {
+            assert!(std::panic::catch_unwind(|| parse_flags_const(flags)).is_err());
+        }
+    }
+
+    #[test]
+    fn trailing_characters() {
+        for (pattern, idx) in &[("a\\", 1), ("\\", 0), ("(?:a)b\\", 6)] {
+            let mut parser = RegexParser::from_parts(pattern, "").unwrap();
+            let err = parser.validate().unwrap_err();
+            assert_eq!(err.msg, "Unexpected trailing characters", "{}", pattern);
+            assert_eq!(err.idx, *idx, "{}", pattern);
+        }
+        run_test("/a\\/").unwrap_err();
+        run_test("/a\\/u").unwrap_err();
+        let mut parser = RegexParser::from_parts("a\\\\", "").unwrap();
+        parser.validate().unwrap();
+    }
+
+    #[test]
+    fn valid_flags() {
+        for c in VALID_FLAGS {
+            assert!(flag_description(*c).is_some(), "{}", c);
+            RegExFlags::default().add_flag(*c, 0).unwrap();
+        }
+        for c in "abcefhjklnopqrtwxzG".chars() {
+            assert!(flag_description(c).is_none(), "{}", c);
+            RegExFlags::default().add_flag(c, 0).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn hex_escape_values() {
+        let mut parser = RegexParser::new(r"/\x41\xFF[\xff\x00]/u").unwrap();
+        parser.validate().unwrap();
+        let values: Vec<Option<u32>> = parser.escapes().iter().map(|e| e.value).collect();
+        assert_eq!(values, vec![Some(0x41), Some(0xFF), Some(0xFF), Some(0x00)]);
+        assert_eq!(parser.escapes()[1].span, (4, 8));
+        for re in &[r"/\xG0/u", r"/\x4/u", r"/[\x4]/u"] {
+            let err = run_test(re).unwrap_err();
+            assert_eq!(err.msg, "Invalid escape", "{}", re);
+        }
+        let mut parser = RegexParser::new(r"/\xG0/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.escapes()[0].value, Some(0x78));
+        assert_eq!(parser.escapes()[0].span, (0, 2));
+    }
+
+    #[test]
+    fn short_hex_escape_fallback() {
+        let mut parser = RegexParser::new(r"/\xZZ/").unwrap();
+        let pattern = parser.parse().unwrap();
+        let terms: Vec<String> = pattern.disjunction.alternatives[0]
+            .terms
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(terms, vec![r"\x", "Z", "Z"]);
+        assert_eq!(parser.escapes()[0].value, Some(u32::from('x')));
+        let mut parser = RegexParser::new(r"/\x4G/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.escapes().len(), 1);
+        assert_eq!(parser.escapes()[0].span, (0, 2));
+        run_test(r"/\xZZ/u").unwrap_err();
+        run_strict_test(r"/\xZZ/").unwrap_err();
+    }
+
+    #[test]
+    fn top_level_branches() {
+        for (re, count) in &[
+            ("/a|b|c/", 3),
+            ("/a(b|c)/", 1),
+            ("//", 1),
+            ("/|/", 2),
+            ("/[|]|(?=a|b)|(?:c|d)/", 3),
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(parser.top_level_branch_count(), *count, "{}", re);
+        }
+    }
+
+    #[test]
+    fn wide_alternation() {
+        // alternatives are parsed in a loop so this doesn't grow the
+        // stack, unlike deeply nested groups which recurse
+        let re = format!("/{}a/", "a|".repeat(100_000));
+        let mut parser = RegexParser::new(&re).unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.top_level_branch_count(), 100_001);
+        let pattern = parser.parse().unwrap();
+        assert_eq!(pattern.disjunction.alternatives.len(), 100_001);
+    }
+
+    #[test]
+    fn unbounded_quantifiers() {
+        let mut parser = RegexParser::new("/a*b+c{2,}d{2}e{1,3}f?(?:g){0,}?/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(
+            parser.unbounded_quantifiers(),
+            vec![
+                (1, UnboundedKind::Star),
+                (3, UnboundedKind::Plus),
+                (5, UnboundedKind::AtLeast(2)),
+                (26, UnboundedKind::AtLeast(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn k_escape_modes() {
+        // without named groups `\k` is an identity escape
+        for re in &[r"/\k/", r"/\ka/", r"/[\k]/"] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(parser.escapes()[0].value, Some(u32::from('k')), "{}", re);
+        }
+        // with named groups (or `u`) it must be followed by a `<name>`
+        for (re, idx) in &[(r"/(?<a>x)\k/", 9), (r"/(?<a>x)\ka/", 9), (r"/\k/u", 2)] {
+            let err = run_test(re).unwrap_err();
+            assert_eq!(err.msg, "Invalid named reference", "{}", re);
+            assert_eq!(err.idx, *idx, "{}", re);
+        }
+        run_strict_test(r"/\k/").unwrap_err();
+    }
+
+    #[test]
+    fn long_or_sequences() {
+        run_test(r#"/((?:[^BEGHLMOSWYZabcdhmswyz']+)|(?:'(?:[^']|'')*')|(?:G{1,5}|y{1,4}|Y{1,4}|M{1,5}|L{1,5}|w{1,2}|W{1}|d{1,2}|E{1,6}|c{1,6}|a{1,5}|b{1,5}|B{1,5}|h{1,2}|H{1,2}|m{1,2}|s{1,2}|S{1,3}|z{1,4}|Z{1,5}|O{1,4}))([\s\S]*)/"#).unwrap();
+    }
+
+    #[test]
+    fn class_set_strings() {
+        for re in &[
+            r"/[\p{RGI_Emoji}a-z]/v",
+            r"/[[a-z]--[aeiou]]/v",
+            r"/[\w&&\d]/v",
+            r"/[^\q{ab}&&\q{a}]/v",
+            r"/[^\p{RGI_Emoji}&&\q{a}]/v",
+            r"/[^\q{a|b}]/v",
+            r"/[\q{abc|}\(]/v",
+            r"/\p{RGI_Emoji}/v",
+        ] {
+            run_test(re).unwrap_or_else(|e| panic!("{}: {}", re, e));
+        }
+        for (re, idx, msg) in &[
+            (r"/[a-\p{L}]/v", 2, "Invalid character class"),
+            (r"/[a-\p{RGI_Emoji}]/v", 2, "Invalid character class"),
+            (
+                r"/[^\q{ab}]/v",
+                0,
+                "Negated character class may contain strings",
+            ),
+            (
+                r"/[^\p{RGI_Emoji}]/v",
+                0,
+                "Negated character class may contain strings",
+            ),
+            (
+                r"/[^[\q{ab}]--a]/v",
+                0,
+                "Negated character class may contain strings",
+            ),
+            (
+                r"/\P{RGI_Emoji}/v",
+                3,
+                "Properties of strings can't be negated",
+            ),
+            (r"/[ab--c]/v", 3, "Invalid set operation in character class"),
+            (
+                r"/[a&&b--c]/v",
+                5,
+                "Invalid set operation in character class",
+            ),
+            (r"/[a!!]/v", 2, "Invalid set operation in character class"),
+            (r"/[(]/v", 1, "Invalid character in character class"),
+        ] {
+            let err = run_test(re).unwrap_err();
+            assert_eq!((err.idx, err.msg.as_str()), (*idx, *msg), "{}", re);
+        }
+        run_test(r"/\p{RGI_Emoji}/u").unwrap_err();
+    }
+
+    #[test]
+    fn class_set_ast() {
+        use ast::{Atom, ClassItem, ClassOperation, TermKind};
+        for re in &[
+            r"/[a--b]/v",
+            r"/[^\w&&[a-z]]/v",
+            r"/[[ab][^c]\q{x|y\|z|}]/v",
+            r"/[\p{L}a-z\(]/v",
+            r"/[\p{RGI_Emoji}a-z\(]/v",
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            let pattern = parser.parse().unwrap();
+            assert_eq!(pattern.to_string(), &re[1..re.len() - 2], "{}", re);
+        }
+        let mut parser = RegexParser::new(r"/[\w--_]/v").unwrap();
+        assert_eq!(
+            parser.describe().unwrap(),
+            r"/[\w--_]/ means: a word character but not '_'"
+        );
+        let mut parser = RegexParser::new(r"/[^\w&&[a-z\q{bc|d}]]/v").unwrap();
+        let pattern = parser.parse().unwrap();
+        let class = match &pattern.disjunction.alternatives[0].terms[0].kind {
+            TermKind::Atom(Atom::Class(class)) => class,
+            other => panic!("expected a class found {:?}", other),
+        };
+        assert!(class.negated);
+        assert_eq!(class.operation, ClassOperation::Intersection);
+        assert_eq!(class.items.len(), 2);
+        let nested = match &class.items[1] {
+            ClassItem::Class(nested) => nested,
+            other => panic!("expected a nested class found {:?}", other),
+        };
+        assert_eq!(nested.span, (6, 19));
+        assert_eq!(nested.operation, ClassOperation::Union);
+        assert_eq!(
+            nested.items[1],
+            ClassItem::Strings {
+                span: (10, 18),
+                strings: vec!["bc".to_string(), "d".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn suggest_without_unicode() {
+        for re in &[r"/\1/u", r"/\-/u", r"/[\d-a]/u", r"/{/v", r"/\p{L/u"] {
+            let err = run_test(re).unwrap_err();
+            assert!(err.suggest_without_unicode, "{}", re);
+        }
+        for re in &[r"/a)/u", r"/a)/", r"/(?<a>)\k<b>/u"] {
+            let err = run_test(re).unwrap_err();
+            assert!(!err.suggest_without_unicode, "{}", re);
+        }
+    }
+
+    #[test]
+    fn astral_class_ranges() {
+        for re in &[
+            r"/[\u{1F600}-\u{1F64F}]/u",
+            r"/[\uD83D\uDE00-\uD83D\uDE4F]/u",
+            "/[😀-🙏]/u",
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            let span = (0, parser.pattern.len());
+            assert_eq!(
+                parser.class_as_char_set(span),
+                Some(vec![(0x1F600, 0x1F64F)]),
+                "{}",
+                re
+            );
+        }
+        // crossing the end of the BMP
+        run_test(r"/[￿-\u{10000}]/u").unwrap();
+        for re in &[
+            r"/[\u{1F64F}-\u{1F600}]/u",
+            r"/[\u{10000}-￿]/u",
+            r"/[🙏-😀]/u",
+        ] {
+            let err = run_test(re).unwrap_err();
+            assert!(err.msg.starts_with("Range out of order"), "{}", re);
+        }
+    }
+
+    #[test]
+    fn literal_prefixes() {
+        for (re, expected) in &[
+            ("/(foo|bar)x/", Some(vec!["foo", "bar"])),
+            ("/^ab(?:c)d+e/", Some(vec!["abcd"])),
+            ("/a(b|c)|a(b|d)/", Some(vec!["ab", "ac", "ad"])),
+            (r"/\x41b?c/", Some(vec!["A"])),
+            ("/ab|c*/", None),
+            ("/(?=a)a/", None),
+            ("/[ab]c/", None),
+            ("/abc/i", None),
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            let expected = expected
+                .as_ref()
+                .map(|list| list.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+            assert_eq!(parser.literal_prefixes(), expected, "{}", re);
+        }
+        assert_eq!(RegexParser::new("/a/").unwrap().literal_prefixes(), None);
+    }
+
+    #[test]
+    fn lazy_quantifiers() {
+        for (re, min, max) in &[
+            ("a*", 0, None),
+            ("a+", 1, None),
+            ("a?", 0, Some(1)),
+            ("a{2,3}", 2, Some(3)),
+        ] {
+            for &greedy in &[true, false] {
+                let lazy = if greedy { "" } else { "?" };
+                let js = format!("/{}{}b/", re, lazy);
+                let mut parser = RegexParser::new(&js).unwrap();
+                parser.validate().unwrap();
+                let quants = parser.quantifiers();
+                assert_eq!(quants.len(), 1, "{}", js);
+                let q = &quants[0];
+                assert_eq!((q.min, q.max, q.greedy), (*min, *max, greedy), "{}", js);
+                assert_eq!((q.at, q.target_span), (1, (0, 1)), "{}", js);
+                // the lazy `?` is part of the quantifier token
+                let token = parser
+                    .state
+                    .tokens
+                    .iter()
+                    .find(|t| t.kind == TokenKind::Quantifier)
+                    .unwrap();
+                assert_eq!(token.span, (1, re.len() + lazy.len()), "{}", js);
+            }
+        }
+    }
+
+    #[test]
+    fn legacy_octal_warning() {
+        let options = ParserOptions {
+            warn_legacy_octal: true,
+            ..ParserOptions::default()
+        };
+        let mut parser = RegexParser::with_options(r"/a\101[\7]/", options).unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.escapes()[0].value, Some(0x41));
+        assert_eq!(
+            parser.warnings(),
+            &[
+                Warning {
+                    idx: 1,
+                    span: None,
+                    msg: r"Legacy octal escape \101; use \x41 or \u{41}".to_string(),
+                },
+                Warning {
+                    idx: 6,
+                    span: None,
+                    msg: r"Legacy octal escape \7; use \x07 or \u{7}".to_string(),
+                },
+            ]
+        );
+        // off by default, even when collecting other warnings
+        let warnings = run_warnings_test(r"/\101/").unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn back_reference_or_octal() {
+        for (re, back_reference, value) in &[
+            (r"/(a)\1/", true, None),
+            (r"/\1/", false, Some(0x01)),
+            (r"/\1(a)/", false, Some(0x01)),
+            (r"/(a)\2/", false, Some(0x02)),
+            (r"/\1(a)/u", true, None),
+            (r"/(?<n>a)\k<n>/", true, None),
+            (r"/[\1]/", false, Some(0x01)),
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            let escape = &parser.escapes()[0];
+            assert_eq!(
+                (escape.back_reference, escape.value),
+                (*back_reference, *value),
+                "{}",
+                re
+            );
+        }
+        let mut parser = RegexParser::new(r"/(a)\1\2/").unwrap();
+        let pattern = parser.parse().unwrap();
+        let terms = &pattern.disjunction.alternatives[0].terms;
+        assert!(matches!(
+            terms[1].kind,
+            ast::TermKind::Atom(ast::Atom::BackReference(_))
+        ));
+        assert!(matches!(
+            terms[2].kind,
+            ast::TermKind::Atom(ast::Atom::Escape(ast::Escape {
+                kind: ast::EscapeKind::Character(0x02),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn empty_reference_name() {
+        for (re, idx) in &[(r"/(?<a>x)\k<>/", 10), (r"/\k<>/u", 3)] {
+            let err = run_test(re).unwrap_err();
+            assert_eq!(err.msg, "Empty group name in reference", "{}", re);
+            assert_eq!(err.idx, *idx, "{}", re);
+        }
+        // without named groups `\k` is an identity escape
+        run_test(r"/\k<>/").unwrap();
+    }
+
+    #[test]
+    fn literal_edge_cases() {
+        for js in &[
+            "",
+            "/",
+            "//",
+            "/g",
+            "g/",
+            r"/\/",
+            r"/\//",
+            "/a/é",
+            "/é/gé",
+            "/a/\u{1F600}",
+        ] {
+            let _ = RegexParser::new(js).and_then(|mut parser| parser.validate());
+        }
+        run_test(r"/\//").unwrap();
+        run_test("//").unwrap();
+        assert_eq!(RegexParser::new("/a/é").err().unwrap().idx, 3);
+        assert_eq!(RegexParser::new("/a/gé").err().unwrap().idx, 4);
+    }
+
+    #[test]
+    fn report() {
+        fn assert_send<T: Send + 'static>(_: &T) {}
+        let report = {
+            let js = String::from(r"/(?<y>\d{4})/u");
+            let mut parser = RegexParser::new(&js).unwrap();
+            parser.validate().unwrap();
+            parser.report()
+        };
+        assert_send(&report);
+        assert_eq!(report.capture_count, 1);
+        assert_eq!(report.group_names, vec!["y".to_string()]);
+        assert!(report.flags.unicode() && !report.flags.global());
+        assert!(!report.uses_lookaround);
+        assert_eq!(report.max_back_reference, 0);
+        assert_eq!(report.min_len, Some(4));
+        assert!(report.warnings.is_empty());
+        for (re, min_len) in &[
+            ("/a|bc/", Some(1)),
+            ("/(?=ab)c*(d)+\\1/", Some(1)),
+            ("/x{2}(?:yz){3,}/", Some(8)),
+            ("/a[]/", None),
+            ("/a[]?/", Some(1)),
+            ("/[a--b]/v", Some(1)),
+            (r"/a[[b]\q{cd}]/v", Some(1)),
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(parser.report().min_len, *min_len, "{}", re);
+        }
     }
 
-    fn eat(&mut self, ch: char) -> bool {
-        if let Some(next) = self.chars.peek() {
-            if *next == ch {
-                self.advance();
-                return true;
-            }
+    #[test]
+    fn class_dash_positions() {
+        for (re, expected) in &[
+            ("/[a-]/", vec![(0x61, 0x61), (0x2D, 0x2D)]),
+            ("/[-a]/", vec![(0x2D, 0x2D), (0x61, 0x61)]),
+            ("/[a-z-]/", vec![(0x61, 0x7A), (0x2D, 0x2D)]),
+            ("/[a-z-]/u", vec![(0x61, 0x7A), (0x2D, 0x2D)]),
+            ("/[-]/", vec![(0x2D, 0x2D)]),
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            let span = (0, parser.pattern.len());
+            assert_eq!(
+                parser.class_as_char_set(span).as_ref(),
+                Some(expected),
+                "{}",
+                re
+            );
         }
-        false
+        // a lone `-` must be escaped with the `v` flag
+        for re in &["/[a-]/v", "/[-a]/v"] {
+            run_test(re).unwrap_err();
+        }
+        run_test(r"/[a\-]/v").unwrap();
     }
 
-    fn advance(&mut self) {
-        if let Some(ch) = self.chars.next() {
-            self.state.pos += ch.len_utf8();
-            log::debug!("adv: {} ({})", ch, self.state.pos);
-        } else {
-            log::debug!("adv at end");
+    #[test]
+    fn referenced_captures() {
+        for (re, groups, referenced) in &[
+            ("/a/", false, false),
+            ("/(a)/", true, false),
+            (r"/(a)\1/", true, true),
+            (r"/(?<x>a)\k<x>/", true, true),
+            (r"/(?:a)\1/", false, false),
+            (r"/(a)[\1]/", true, false),
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(parser.has_capturing_groups(), *groups, "{}", re);
+            assert_eq!(parser.has_referenced_captures(), *referenced, "{}", re);
         }
     }
 
-    fn reset_to(&mut self, idx: usize) {
-        let remaining = &self.pattern[idx..];
-        self.chars = remaining.chars().peekable();
-        log::debug!("res: {} ({})", self.chars.peek().unwrap_or(&' '), idx);
-        self.state.pos = idx;
+    #[test]
+    fn validate_matches_parse() {
+        for re in &[
+            "/a|(b)+/",
+            r"/(?<a>x)\k<a>/u",
+            "/a)/",
+            "/[b-a]/",
+            r"/\k<a>/u",
+        ] {
+            let validated = RegexParser::new(re).unwrap().validate();
+            let parsed = RegexParser::new(re).unwrap().parse().map(|_| ());
+            assert_eq!(
+                validated.map_err(|e| e.to_string()),
+                parsed.map_err(|e| e.to_string()),
+                "{}",
+                re
+            );
+        }
     }
-}
 
-struct State<'a> {
-    pos: usize,
-    len: usize,
-    last_int_value: Option<u32>,
-    last_string_value: Option<&'a str>,
-    last_assert_is_quant: bool,
-    num_capturing_parens: u32,
-    max_back_refs: u32,
-    group_names: Vec<&'a str>,
-    back_ref_names: Vec<&'a str>,
-    n: bool,
-    u: bool,
-}
+    #[test]
+    fn from_parts() {
+        for (pattern, flags) in &[("", ""), ("a/b", ""), (r"\u{61}", "u"), ("[a-c]", "dv")] {
+            let mut parser = RegexParser::from_parts(pattern, flags).unwrap();
+            parser
+                .validate()
+                .unwrap_or_else(|e| panic!("{} {}: {}", pattern, flags, e));
+        }
+        let err = RegexParser::from_parts("a)", "g")
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!((err.idx, err.space), (2, IndexSpace::Pattern));
+        let err = RegexParser::from_parts("a", "gx").err().unwrap();
+        assert_eq!((err.idx, err.space), (1, IndexSpace::Flags));
+        let err = RegexParser::from_parts("a", "uv").err().unwrap();
+        assert_eq!((err.idx, err.space), (1, IndexSpace::Flags));
+        let parser = RegexParser::from_parts("a", "is").unwrap();
+        assert_eq!(*parser.flags(), parse_flags_const("si"));
+    }
 
-impl<'a> State<'a> {
-    pub fn new(len: usize, u: bool) -> Self {
-        Self {
-            pos: 0,
-            len,
-            last_int_value: None,
-            last_string_value: None,
-            last_assert_is_quant: false,
-            num_capturing_parens: 0,
-            max_back_refs: 0,
-            group_names: Vec::new(),
-            back_ref_names: Vec::new(),
-            n: u,
-            u,
+    #[test]
+    fn group_names() {
+        for re in &[
+            r"/(?<a>x)(b)(?:(?<c>y))\k<a>/",
+            r"/(?<a>x)(b)(?:(?<c>y))\k<a>/u",
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            assert!(parser.group_names().is_empty());
+            parser.validate().unwrap();
+            assert_eq!(parser.group_names(), vec!["a", "c"], "{}", re);
+            // validating again gives the same names
+            parser.validate().unwrap();
+            assert_eq!(parser.group_names(), vec!["a", "c"], "{}", re);
         }
+        let mut parser = RegexParser::new("/(a)/").unwrap();
+        parser.validate().unwrap();
+        assert!(parser.group_names().is_empty());
     }
-    pub fn reset(&mut self) {
-        self.pos = 0;
-        self.last_int_value = None;
-        self.last_string_value = None;
-        self.num_capturing_parens = 0;
-        self.max_back_refs = 0;
-        self.group_names.clear();
-        self.back_ref_names.clear();
+
+    #[test]
+    fn has_indices_flag() {
+        for re in &["/abc/d", "/a(b)c/dg", "/(?<x>a)/du"] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser.validate().unwrap();
+            assert!(parser.flags().has_indices(), "{}", re);
+        }
+        assert!(!RegexParser::new("/abc/g").unwrap().flags().has_indices());
+        let err = RegexParser::new("/abc/dgd").err().unwrap();
+        assert_eq!((err.idx, err.msg.as_str()), (7, "duplicate d flag"));
     }
-}
 
-#[derive(Debug)]
-struct RegExFlags {
-    case_insensitive: bool,
-    multi_line: bool,
-    dot_matches_new_line: bool,
-    unicode: bool,
-    global: bool,
-    sticky: bool,
-    has_indicies: bool,
-}
+    #[test]
+    fn unicode_sets_mode() {
+        for re in &[
+            "/[[a-z]&&[^aeiou]]/v",
+            "/[[a-z]--[aeiou]]/v",
+            "/[[a-z][0-9]_]/v",
+            r"/[\d--[5-9]]+/v",
+            r"/[[[a]]]/v",
+            r"/\u{1F600}/v",
+        ] {
+            let mut parser = RegexParser::new(re).unwrap();
+            parser
+                .validate()
+                .unwrap_or_else(|e| panic!("{}: {}", re, e));
+            assert!(parser.is_unicode_mode(), "{}", re);
+        }
+        // the `u` flag rules outside of classes still apply
+        for re in &[r"/\-/v", "/{/v", r"/\1/v", "/(?=a)*/v", "/[[a]/v"] {
+            run_test(re).unwrap_err();
+        }
+        // set notation is only special with `v`
+        run_test("/[[a-z]&&[^aeiou]]/").unwrap();
+        run_test("/[[a-z]&&[^aeiou]]/u").unwrap_err();
+        let err = RegexParser::new("/a/uv").err().unwrap();
+        assert_eq!(err.msg, "u and v flags are mutually exclusive");
+        let err = RegexParser::new("/a/vu").err().unwrap();
+        assert_eq!(err.idx, 4);
+    }
 
-impl Default for RegExFlags {
-    fn default() -> Self {
-        RegExFlags {
-            case_insensitive: false,
-            multi_line: false,
-            dot_matches_new_line: false,
-            unicode: false,
-            global: false,
-            sticky: false,
-            has_indicies: false,
+    #[test]
+    fn error_line_and_column() {
+        let err = run_test("/ab)/").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 4));
+        assert_eq!(err.to_string(), "Unmatched `)` at 1:4");
+        // columns count characters, not bytes
+        let err = run_test("/éé)/").unwrap_err();
+        assert_eq!((err.idx, err.line, err.column), (5, 1, 4));
+        let err = RegexParser::from_parts("a\nbc\n[", "")
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!((err.line, err.column), (3, 2));
+        let err = RegexParser::new("/a/\ngg").err().unwrap();
+        assert_eq!((err.idx, err.line, err.column), (3, 1, 4));
+        let err = RegexParser::from_parts("a", "gig").err().unwrap();
+        assert_eq!((err.line, err.column), (1, 3));
+        let err = RegexParser::from_parts("a\n\\p{Foo}", "u")
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidUnicodeProperty);
+        assert_eq!((err.idx, err.line, err.column), (8, 2, 7));
+        let err = RegexParser::new("/a\n\\p{Script=Foo}/u")
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert_eq!((err.line, err.column), (2, 14));
+    }
+
+    #[test]
+    fn error_kinds() {
+        let cases = [
+            ("/a)/", ErrorKind::UnmatchedParen),
+            ("/*/", ErrorKind::NothingToRepeat),
+            ("/\\-/u", ErrorKind::InvalidEscape),
+            ("/a/gg", ErrorKind::DuplicateFlag),
+            ("/[b-a]/", ErrorKind::RangeOutOfOrder),
+            ("/\\p{Foo}/u", ErrorKind::InvalidUnicodeProperty),
+            ("/(a/", ErrorKind::UnterminatedGroup),
+            ("/a/x", ErrorKind::InvalidFlag),
+        ];
+        for (regex, kind) in cases.iter() {
+            let err = run_test(regex).unwrap_err();
+            assert_eq!(err.kind, *kind, "{} -> {}", regex, err);
         }
     }
-}
 
-impl RegExFlags {
-    fn add_flag(&mut self, c: char, pos: usize) -> Result<(), Error> {
-        match c {
-            'g' => {
-                if self.global {
-                    Err(Error::new(pos, "duplicate g flag"))
-                } else {
-                    self.global = true;
-                    Ok(())
-                }
-            }
-            'i' => {
-                if self.case_insensitive {
-                    Err(Error::new(pos, "duplicate i flag"))
-                } else {
-                    self.case_insensitive = true;
-                    Ok(())
-                }
-            }
-            'm' => {
-                if self.multi_line {
-                    Err(Error::new(pos, "duplicate m flag"))
-                } else {
-                    self.multi_line = true;
-                    Ok(())
-                }
-            }
-            's' => {
-                if self.dot_matches_new_line {
-                    Err(Error::new(pos, "duplicate s flag"))
-                } else {
-                    self.dot_matches_new_line = true;
-                    Ok(())
-                }
-            }
-            'u' => {
-                if self.unicode {
-                    Err(Error::new(pos, "duplicate u flag"))
-                } else {
-                    self.unicode = true;
-                    Ok(())
-                }
-            }
-            'y' => {
-                if self.sticky {
-                    Err(Error::new(pos, "duplicate y flag"))
-                } else {
-                    self.sticky = true;
-                    Ok(())
-                }
-            }
-            'd' => {
-                if self.has_indicies {
-                    Err(Error::new(pos, "duplicate d flag"))
-                } else {
-                    self.has_indicies = true;
-                    Ok(())
-                }
-            }
-            _ => Err(Error::new(pos, &format!("invalid flag {:?}", c))),
+    #[test]
+    fn multi_byte_resets() {
+        let errors = [
+            "/café\\u{}/u",
+            "/café\\u{110000}/u",
+            "/é\\x4/u",
+            "/é\\u00e/u",
+            "/日本\\p{Foo}/u",
+            "/(?<日>a)\\k<本>/",
+            "/[é-\\u{}]/u",
+            "/ü{2,1}/",
+            "/𝒳\\c/u",
+        ];
+        for regex in errors.iter() {
+            assert!(run_test(regex).is_err(), "{}", regex);
+        }
+        let valid = [
+            "/café\\u{}/",
+            "/é\\x4/",
+            "/é\\u00e/",
+            "/ü{2/",
+            "/𝒳\\c/",
+            "/日本\\k<本>/",
+            "/[é\\u{]/",
+        ];
+        for regex in valid.iter() {
+            run_test(regex).unwrap();
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
     #[test]
-    fn lots_of_regexes() {
-        run_test("/asdf|fdsa/g").unwrap();
+    fn reset_with() {
+        let mut parser = RegexParser::from_parts("(?<a>b)\\k<a>", "").unwrap();
+        parser.validate().unwrap();
+        // \\k is only a named reference with named groups or `u`
+        parser.reset_with("\\k", "").unwrap();
+        parser.validate().unwrap();
+        assert!(parser.group_names().is_empty());
+        parser.reset_with("\\k", "u").unwrap();
+        assert!(parser.validate().is_err());
+        assert!(parser.flags().unicode);
+        parser.reset_with("[a&&b]", "v").unwrap();
+        parser.validate().unwrap();
+        assert!(parser.reset_with("a", "gg").is_err());
+        // a rejected reset leaves the previous pattern in place
+        parser.validate().unwrap();
+        assert!(parser.flags().unicode_sets);
+        for (pattern, flags) in [("a(b)c", "i"), ("[\\d-z]", ""), ("x{2}?", "y")].iter() {
+            let mut fresh = RegexParser::from_parts(pattern, flags).unwrap();
+            fresh.validate().unwrap();
+            parser.reset_with(pattern, flags).unwrap();
+            parser.validate().unwrap();
+            assert_eq!(parser.report(), fresh.report());
+        }
+
+        let mut limited = RegexParser::with_options(
+            "/a/",
+            ParserOptions {
+                max_pattern_len: Some(3),
+                max_operations: Some(50),
+                ..ParserOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            limited.reset_with("abcd", "").unwrap_err().kind,
+            ErrorKind::PatternTooLong
+        );
+        // the operation budget applies to each pattern
+        for _ in 0..10 {
+            limited.reset_with("abc", "").unwrap();
+            limited.validate().unwrap();
+        }
+        // new flags are parsed with the parser's options
+        let mut older = RegexParser::with_version("/a/u", EcmaVersion::Es2015).unwrap();
+        let err = older.reset_with("[a&&b]", "v").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::NewerThanTarget);
+        assert_eq!((err.idx, err.space), (0, IndexSpace::Flags));
+        assert!(older.flags().unicode);
+        older.reset_with("a", "gi").unwrap();
+        let mut collecting = RegexParser::with_options(
+            "/a/",
+            ParserOptions {
+                collect_flag_errors: true,
+                ..ParserOptions::default()
+            },
+        )
+        .unwrap();
+        let err = collecting.reset_with("a", "gigi").unwrap_err();
+        assert_eq!(err.msg, "duplicate flags: g, i");
     }
+
     #[test]
-    #[should_panic = "Invalid escape"]
-    fn decimal_escape_with_u() {
-        run_test(r"/\1/u").unwrap()
+    fn named_mode_second_pass() {
+        for (regex, valid) in [
+            (r"/(?<a>x)\k<a>/", true),
+            (r"/\k<a>(?<a>x)/", true),
+            (r"/(?<a>x)\k<b>/", false),
+            (r"/(?<a>x)\k/", false),
+            (r"/(?<a>x)(?=y)*/", true),
+            (r"/(?<a>x)(?=y){2}/", true),
+            (r"/(?<a>x)(?=y)/", true),
+            (r"/(?<a>x)|(?<b>y)|z+/", true),
+            (r"/\k(?=y)*/", true),
+        ]
+        .iter()
+        {
+            assert_eq!(run_test(regex).is_ok(), *valid, "{}", regex);
+        }
+        let mut parser = RegexParser::new(r"/(?<a>x)|y{2}/").unwrap();
+        parser.validate().unwrap();
+        assert!(parser.is_named_mode());
+        assert_eq!(parser.group_names(), vec!["a"]);
     }
 
     #[test]
-    #[should_panic = "invalid flag"]
-    fn invalid_regex_flag() {
-        run_test("/./G").unwrap();
+    fn token_iter() {
+        use TokenKind::*;
+        let parser = RegexParser::new(r"/^a|[^b-c\d]\.{2}$/").unwrap();
+        let tokens: Vec<Token> = parser.tokens().map(Result::unwrap).collect();
+        let expected = [
+            (Assertion, (0, 1)),
+            (Literal, (1, 2)),
+            (Alternation, (2, 3)),
+            (ClassOpen, (3, 5)),
+            (Literal, (5, 6)),
+            (ClassRange, (6, 7)),
+            (Literal, (7, 8)),
+            (Escape, (8, 10)),
+            (ClassClose, (10, 11)),
+            (Escape, (11, 13)),
+            (Quantifier, (13, 16)),
+            (Assertion, (16, 17)),
+        ];
+        let expected: Vec<Token> = expected
+            .iter()
+            .map(|&(kind, span)| Token { kind, span })
+            .collect();
+        assert_eq!(tokens, expected);
+
+        let mut iter = RegexParser::new("/ab)c/").unwrap().tokens();
+        assert_eq!(iter.next().unwrap().unwrap().span, (0, 1));
+        assert_eq!(iter.next().unwrap().unwrap().span, (1, 2));
+        let err = iter.next().unwrap().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnmatchedParen);
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
     }
 
     #[test]
-    #[should_panic = "Nothing to repeat"]
-    fn bad_look_behind() {
-        run_test(r"/.(?<=.)?/").unwrap();
+    fn modifier_groups() {
+        for regex in [
+            "/(?i:a)/",
+            "/(?-i:a)/i",
+            "/(?i-ms:a|b)+/",
+            "/(?ms:^.$)/u",
+            "/(?s-i:(?i:a)b)/v",
+        ]
+        .iter()
+        {
+            run_test(regex).unwrap();
+        }
+        for (regex, idx, msg) in [
+            ("/(?g:a)/", 2, "Invalid group flags"),
+            ("/(?iu:a)/", 3, "Invalid group flags"),
+            ("/(?-:a)/", 3, "Invalid group flags"),
+            ("/(?ii:a)/", 3, "duplicate i flag"),
+            ("/(?i-i:a)/", 4, "duplicate i flag"),
+            ("/(?i--m:a)/", 4, "Invalid group"),
+            ("/(?i)a/", 3, "Invalid group"),
+        ]
+        .iter()
+        {
+            let err = run_test(regex).unwrap_err();
+            assert_eq!((err.idx, err.msg.as_str()), (*idx, *msg), "{}", regex);
+        }
+        let mut parser = RegexParser::new("/(?i-m:a)(?s:b)/").unwrap();
+        let pattern = parser.parse().unwrap();
+        assert_eq!(pattern.to_string(), "(?i-m:a)(?s:b)");
+        assert_eq!(
+            pattern.describe(),
+            "(with the i flag and without the m flag 'a'), \
+             then (with the s flag 'b')"
+        );
+        assert_eq!(parser.capture_count(), 0);
+        let mut parser = RegexParser::new("/x(?i:a)|(?-i:y)z/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(
+            parser.literal_prefixes(),
+            Some(vec!["x".to_string(), "yz".to_string()])
+        );
+        let err = crate::is_match("/(?i:a)/", "A").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Unsupported);
     }
 
     #[test]
-    #[should_panic]
-    fn bad_quant() {
-        run_test(r"/{2}/").unwrap();
+    fn large_quantifier_bounds() {
+        for regex in [
+            "/a{4294967295}/",
+            "/a{4294967296}/u",
+            "/a{4294967295,4294967296}/",
+            "/a{4294967296,4294967297}/u",
+            "/a{99999999999999999999,}/",
+            "/a{0004294967296,4294967296}/",
+        ]
+        .iter()
+        {
+            run_test(regex).unwrap();
+        }
+        for regex in [
+            "/a{4294967296,4294967295}/",
+            "/a{4294967297,4294967296}/u",
+            "/a{99999999999999999999,9999999999999999999}/",
+        ]
+        .iter()
+        {
+            let err = run_test(regex).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::InvalidQuantifier, "{}", regex);
+        }
+        let mut parser = RegexParser::new("/a{4294967296,4294967297}b{2,4294967295}/").unwrap();
+        parser.validate().unwrap();
+        let bounds: Vec<_> = parser
+            .quantifiers()
+            .iter()
+            .map(|q| (q.min, q.max))
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![(u32::MAX, Some(u32::MAX)), (2, Some(u32::MAX))]
+        );
+        // other digit sequences are clamped rather than overflowing
+        run_test(r"/(a)\99999999999/").unwrap();
+        assert!(run_test(r"/(a)\99999999999/u").is_err());
+        assert!(run_test(r"/\u{100000000000}/u").is_err());
     }
 
     #[test]
-    #[should_panic]
-    fn id_continue_u() {
-        run_test(r"/\M/u").unwrap();
+    fn quantifier_ast_bounds() {
+        use ast::{Quantifier, TermKind};
+        let mut parser =
+            RegexParser::new("/a{2,5}?b{4294967296,99999999999999999999}c*?d{3,}e+/").unwrap();
+        let pattern = parser.parse().unwrap();
+        let bounds: Vec<(u64, Option<u64>, bool)> = pattern.disjunction.alternatives[0]
+            .terms
+            .iter()
+            .map(|term| match (&term.kind, &term.quantifier) {
+                (
+                    TermKind::Atom(_),
+                    Some(Quantifier {
+                        min, max, greedy, ..
+                    }),
+                ) => (*min, *max, *greedy),
+                _ => panic!("expected a quantified atom"),
+            })
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![
+                (2, Some(5), false),
+                (4_294_967_296, Some(u64::MAX), true),
+                (0, None, false),
+                (3, None, true),
+                (1, None, true),
+            ]
+        );
+        let mut parser = RegexParser::new("/a{4294967296,4294967297}?/").unwrap();
+        assert_eq!(
+            parser.parse().unwrap().to_string(),
+            "a{4294967296,4294967297}?"
+        );
     }
 
+    #[cfg(feature = "serde")]
     #[test]
-    #[should_panic]
-    fn cant_start_with_star() {
-        run_test("/*/").unwrap();
+    fn serde_round_trip() {
+        let mut parser =
+            RegexParser::new(r"/^(?<year>\d{4})-[\w--\q{ab|c}]\k<year>(?i:x)+?$/v").unwrap();
+        let pattern = parser.parse().unwrap();
+        let json = serde_json::to_string(&pattern).unwrap();
+        let read: ast::Pattern = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, pattern);
+        let term = serde_json::to_value(&pattern.disjunction.alternatives[0].terms[0]).unwrap();
+        assert_eq!(term["span"], serde_json::json!([0, 1]));
+
+        let flags = *parser.flags();
+        assert_eq!(serde_json::to_string(&flags).unwrap(), r#""v""#);
+        let flags: RegExFlags = serde_json::from_str(r#""yigd""#).unwrap();
+        assert_eq!(serde_json::to_string(&flags).unwrap(), r#""dgiy""#);
+        assert!(serde_json::from_str::<RegExFlags>(r#""uv""#).is_err());
+        assert!(serde_json::from_str::<RegExFlags>(r#""gg""#).is_err());
     }
 
     #[test]
-    fn unicode_name_and_value() {
-        for value in unicode_tables::general_category::GC {
-            run_test(&format!(r"/\p{{General_Category={}}}/u", value))
-                .expect(&format!("failed at General_category={}", value));
-            run_test(&format!(r"/\p{{gc={}}}/u", value)).expect(&format!("failed at gc={}", value));
+    fn collect_flag_errors() {
+        let options = ParserOptions {
+            collect_flag_errors: true,
+            ..ParserOptions::default()
+        };
+        let cases = [
+            ("/a/gig", 5, ErrorKind::DuplicateFlag, "duplicate g flag"),
+            (
+                "/a/gigii",
+                5,
+                ErrorKind::DuplicateFlag,
+                "duplicate flags: g, i",
+            ),
+            ("/a/gggg", 4, ErrorKind::DuplicateFlag, "duplicate flags: g"),
+            (
+                "/a/gxgzx",
+                4,
+                ErrorKind::InvalidFlag,
+                "duplicate flags: g; invalid flags: 'x', 'z'",
+            ),
+            (
+                "/a/uvu",
+                4,
+                ErrorKind::IncompatibleFlags,
+                "duplicate flags: u; u and v flags are mutually exclusive",
+            ),
+        ];
+        for (regex, idx, kind, msg) in cases.iter() {
+            let err = RegexParser::with_options(regex, options.clone())
+                .err()
+                .unwrap();
+            assert_eq!(
+                (err.idx, err.kind, err.msg.as_str()),
+                (*idx, *kind, *msg),
+                "{}",
+                regex
+            );
+            assert_eq!(err.space, IndexSpace::Literal);
         }
-        for value in unicode_tables::script_values::SCRIPT {
-            run_test(&format!(r"/\p{{Script={}}}/u", value))
-                .expect(&format!("failed at Script={}", value));
-            run_test(&format!(r"/\p{{sc={}}}/u", value)).expect(&format!("failed at sc={}", value));
-            run_test(&format!(r"/\p{{Script_Extensions={}}}/u", value))
-                .expect(&format!("failed at Script_Extensions={}", value));
-            run_test(&format!(r"/\p{{scx={}}}/u", value))
-                .expect(&format!("failed at scx={}", value));
+        // without the option only the first problem is reported
+        let err = RegexParser::new("/a/gigii").err().unwrap();
+        assert_eq!((err.idx, err.msg.as_str()), (5, "duplicate g flag"));
+        RegexParser::with_options("/a/dgimsy", options).unwrap();
+    }
+
+    #[test]
+    fn ecma_version() {
+        let cases = [
+            ("/a/gim", EcmaVersion::Es5),
+            ("/a/uy", EcmaVersion::Es2015),
+            ("/(?<a>b)\\k<a>/s", EcmaVersion::Es2018),
+            ("/(?<=a)(?<!b)/", EcmaVersion::Es2018),
+            ("/\\p{L}/u", EcmaVersion::Es2018),
+            ("/a/d", EcmaVersion::Es2022),
+            ("/[a--b]/v", EcmaVersion::Es2024),
+            ("/(?i:a)/", EcmaVersion::Es2025),
+        ];
+        for (regex, version) in cases.iter() {
+            RegexParser::with_version(regex, *version)
+                .and_then(|mut parser| parser.validate())
+                .unwrap();
         }
+        let errors = [
+            ("/a/u", EcmaVersion::Es5, 3, "the u flag requires ES2015"),
+            (
+                "/a/gs",
+                EcmaVersion::Es2015,
+                4,
+                "the s flag requires ES2018",
+            ),
+            ("/a/d", EcmaVersion::Es2018, 3, "the d flag requires ES2022"),
+            ("/a/v", EcmaVersion::Es2022, 3, "the v flag requires ES2024"),
+            (
+                "/a(?<b>c)/",
+                EcmaVersion::Es2015,
+                1,
+                "Named groups require ES2018",
+            ),
+            (
+                "/(?<=a)/",
+                EcmaVersion::Es2015,
+                0,
+                "Look behind assertions require ES2018",
+            ),
+            (
+                "/a\\p{L}/u",
+                EcmaVersion::Es2015,
+                1,
+                "Property escapes require ES2018",
+            ),
+            (
+                "/[\\p{L}]/u",
+                EcmaVersion::Es2015,
+                1,
+                "Property escapes require ES2018",
+            ),
+            (
+                "/b(?i:a)/",
+                EcmaVersion::Es2024,
+                1,
+                "Modifier groups require ES2025",
+            ),
+        ];
+        for (regex, version, idx, msg) in errors.iter() {
+            let err = RegexParser::with_version(regex, *version)
+                .and_then(|mut parser| parser.validate())
+                .unwrap_err();
+            assert_eq!(err.kind, ErrorKind::NewerThanTarget, "{}", regex);
+            assert_eq!((err.idx, err.msg.as_str()), (*idx, *msg), "{}", regex);
+        }
+        // a lookahead and `\\p` without `u` are unchanged
+        RegexParser::with_version("/(?=a)\\p{L}/", EcmaVersion::Es5)
+            .and_then(|mut parser| parser.validate())
+            .unwrap();
+        let options = ParserOptions {
+            ecma_version: EcmaVersion::Es2015,
+            collect_flag_errors: true,
+            ..ParserOptions::default()
+        };
+        let err = RegexParser::with_options("/a/gsgd", options).err().unwrap();
+        assert_eq!(
+            err.msg,
+            "duplicate flags: g; the s flag requires ES2018; the d flag requires ES2022"
+        );
     }
+
     #[test]
-    #[should_panic]
-    fn unicode_name_and_value_bad_name() {
-        run_test(r"/\p{junk=Greek}/u").unwrap();
+    fn group_spans() {
+        let mut parser = RegexParser::new(r"/(?:(a)|(?<é>b(c)))\1(?=(d))/").unwrap();
+        parser.validate().unwrap();
+        let groups: Vec<_> = parser
+            .groups()
+            .iter()
+            .map(|g| (g.index, g.name, g.span))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (1, None, (3, 6)),
+                (2, Some("é"), (7, 18)),
+                (3, None, (14, 17)),
+                (4, None, (24, 27)),
+            ]
+        );
+        // a named group before the second pass is only recorded once
+        let mut parser = RegexParser::new(r"/(?<a>x)\k<a>/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.groups().len(), 1);
+        // spans after an edit are moved by a region revalidation
+        let mut parser = RegexParser::new("/(?:a)(b)/").unwrap();
+        parser.validate().unwrap();
+        parser.revalidate_region("(?:abc)(b)", 4).unwrap();
+        assert_eq!(parser.groups()[0].span, (7, 10));
     }
+
     #[test]
-    #[should_panic]
-    fn unicode_name_and_value_bad_value() {
-        run_test(r"/\p{General_Category=Geek}/u").unwrap();
+    fn unbounded_lookbehind_warning() {
+        let options = ParserOptions {
+            warn_unbounded_lookbehind: true,
+            ..ParserOptions::default()
+        };
+        let cases: &[(&str, &[usize])] = &[
+            (r"/(?<=a*)b/", &[5]),
+            (r"/(?<!a+|b{2,})c/", &[5, 8]),
+            (r"/(?<=(?:a(b)*))c/", &[11]),
+            (r"/(?<=(?=a*)b)c/", &[8]),
+            (r"/(?<=a?b{2}c{1,3})d*(?=e+)/", &[]),
+        ];
+        for (regex, expected) in cases.iter() {
+            let mut parser = RegexParser::with_options(regex, options.clone()).unwrap();
+            parser.validate().unwrap();
+            let found: Vec<usize> = parser.warnings().iter().map(|w| w.idx).collect();
+            assert_eq!(&found, expected, "{}", regex);
+        }
+        let mut parser = RegexParser::new(r"/(?<=a*)b/").unwrap();
+        parser.validate().unwrap();
+        assert!(parser.warnings().is_empty());
     }
+
     #[test]
-    #[should_panic]
-    fn unicode_name_or_value_bad_value() {
-        run_test(r"/\p{junk}/u").unwrap();
+    fn suspicious_construct_warnings() {
+        let cases: &[(&str, &[(usize, &str)])] = &[
+            (
+                "/a||b/",
+                &[(2, "an empty alternative matches the empty string")],
+            ),
+            (
+                "/|a|/",
+                &[
+                    (0, "an empty alternative matches the empty string"),
+                    (3, "an empty alternative matches the empty string"),
+                ],
+            ),
+            (
+                "/(a|)b/",
+                &[(3, "an empty alternative matches the empty string")],
+            ),
+            ("/[aba]/", &[(3, "duplicate a in character class")]),
+            (r"/[\x41A\d\d]/", &[(5, "duplicate A in character class")]),
+            (
+                r"/\q[\q]/",
+                &[
+                    (0, r"unnecessary escape \q matches 'q'"),
+                    (3, r"unnecessary escape \q matches 'q'"),
+                ],
+            ),
+            ("/a|b|(c)/", &[]),
+            ("/[a-ca]/", &[]),
+            (r"/\t\-\d/", &[]),
+        ];
+        for (regex, expected) in cases.iter() {
+            let warnings = run_warnings_test(regex).unwrap();
+            let found: Vec<(usize, &str)> =
+                warnings.iter().map(|w| (w.idx, w.msg.as_str())).collect();
+            assert_eq!(&found, expected, "{}", regex);
+        }
+        // none of these are errors
+        for regex in ["/a||b/", "/[aa]/", r"/\q/"].iter() {
+            run_test(regex).unwrap();
+        }
     }
+
     #[test]
-    fn unicode_name_or_value() {
-        for value in unicode_tables::GC_AND_BP {
-            run_test(&format!(r"/\p{{{}}}/u", value)).unwrap();
+    fn case_gap_range_warning() {
+        let cases: &[(&str, &[(usize, usize)])] = &[
+            ("/[A-z]/", &[(1, 4)]),
+            (r"/a[_0-z\x41-\x7a]/", &[(3, 6), (6, 15)]),
+            ("/[A-z]/u", &[(1, 4)]),
+            ("/[A-Za-z]/", &[]),
+            ("/[!-~]/", &[]),
+            ("/[A-Z_a-z]/", &[]),
+            ("/[a-z]/", &[]),
+        ];
+        for (regex, expected) in cases.iter() {
+            let warnings = run_warnings_test(regex).unwrap();
+            let spans: Vec<(usize, usize)> = warnings.iter().filter_map(|w| w.span).collect();
+            assert_eq!(&spans, expected, "{}", regex);
+            for w in &warnings {
+                assert_eq!(Some(w.idx), w.span.map(|s| s.0));
+            }
         }
+        let warnings = run_warnings_test("/[A-z]/").unwrap();
+        assert_eq!(
+            warnings[0].msg,
+            "range A-z also matches the punctuation between Z and a ([\\]^_`)"
+        );
+        run_test("/[A-z]/").unwrap();
     }
 
     #[test]
-    fn named_group() {
-        run_test(r"/(?<x>a)|b/").unwrap();
+    fn back_references() {
+        let mut parser = RegexParser::new(r"/(a)\1[\1]\2(?<b>c)\k<b>/").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(
+            parser.back_references(),
+            vec![
+                BackRef::Numeric {
+                    index: 1,
+                    span: (3, 5)
+                },
+                BackRef::Named {
+                    name: "b",
+                    span: (18, 23)
+                },
+            ]
+        );
+        // with the `u` flag a forward reference is still a reference
+        let mut parser = RegexParser::new(r"/\2(a)(b)/u").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.back_references()[0].span(), (0, 2));
+        // spans after an edit are moved by a region revalidation
+        let mut parser = RegexParser::new(r"/(?:a)(b)\1/").unwrap();
+        parser.validate().unwrap();
+        parser.revalidate_region(r"(?:abc)(b)\1", 4).unwrap();
+        assert_eq!(parser.back_references()[0].span(), (10, 12));
     }
 
     #[test]
-    fn control_range() {
-        run_test(r"/[\t-\r]/").unwrap();
+    fn decimal_escape_resolution() {
+        use DecimalResolution::*;
+        let resolve = |regex: &str| {
+            let mut parser = RegexParser::new(regex).unwrap();
+            parser.validate().unwrap();
+            let escapes = parser.decimal_escapes();
+            assert_eq!(escapes.len(), 1, "{}", regex);
+            escapes[0].resolution
+        };
+        let expected = [
+            (Null, Null),
+            (LegacyOctal, BackReference),
+            (LegacyOctal, BackReference),
+            (LegacyOctal, BackReference),
+            (LegacyOctal, BackReference),
+            (LegacyOctal, BackReference),
+            (LegacyOctal, BackReference),
+            (LegacyOctal, BackReference),
+            (Identity, BackReference),
+            (Identity, BackReference),
+        ];
+        for (digit, (without, with)) in expected.iter().enumerate() {
+            let alone = format!("/\\{}/", digit);
+            assert_eq!(resolve(&alone), *without, "{}", alone);
+            let grouped = format!("/(a)(b)(c)(d)(e)(f)(g)(h)(i)\\{}/", digit);
+            assert_eq!(resolve(&grouped), *with, "{}", grouped);
+            let unicode = format!("{}u", grouped);
+            assert_eq!(resolve(&unicode), *with, "{}", unicode);
+        }
+        // a digit in a class is never a back reference
+        assert_eq!(resolve(r"/(a)[\1]/"), LegacyOctal);
+        assert_eq!(resolve(r"/(a)\012/"), LegacyOctal);
+        // 18 is more than the number of groups, so this is \1 then 8
+        assert_eq!(resolve(r"/(a)\18/"), LegacyOctal);
     }
 
     #[test]
-    fn known_flags() {
-        let flags = &['d', 'g', 'i', 'm', 's', 'u', 'y'];
-        for flag in flags {
-            run_test(&format!("/.+/{}", flag)).unwrap();
+    fn surrogates_in_group_names() {
+        for regex in [
+            r"/(?<\uD835\uDC9C>a)\k<\uD835\uDC9C>/u",
+            r"/(?<a\uD835\uDC9C>a)/u",
+            r"/(?<\u{1D49C}>a)/u",
+        ]
+        .iter()
+        {
+            run_test(regex).unwrap();
+        }
+        let cases: &[(&str, usize)] = &[
+            (r"/(?<\uD800>a)/u", 3),
+            (r"/(?<a\uDC00>a)/u", 4),
+            (r"/(?<\uDC9C\uD835>a)/u", 3),
+            (r"/(?<a>b)\k<\uD835>/u", 10),
+        ];
+        for (regex, idx) in cases.iter() {
+            let err = run_test(regex).unwrap_err();
+            assert_eq!(err.idx, *idx, "{}", regex);
+            assert_eq!(err.msg, "Invalid surrogate in identifier", "{}", regex);
         }
-        let all: String = flags.iter().collect();
-        run_test(&format!("/.+/{}", all)).unwrap();
     }
 
     #[test]
-    fn duplicate_known_flags() {
-        let flags = &['d', 'g', 'i', 'm', 's', 'u', 'y'];
-        for flag in flags {
-            run_test(&format!("/.+/{0}{}0", flag)).unwrap_err();
+    fn owned_parser() {
+        let handles: Vec<_> = ["(?<a>b)\\k<a>", "a{2,1}", "[\\p{L}--\\q{a}]"]
+            .iter()
+            .map(|pattern| {
+                let mut parser =
+                    RegexParser::new_owned(pattern.to_string(), String::from("v")).unwrap();
+                std::thread::spawn(move || {
+                    let result = parser.validate().map_err(|e| e.idx);
+                    let names: Vec<String> =
+                        parser.group_names().into_iter().map(String::from).collect();
+                    (result, names)
+                })
+            })
+            .collect();
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(
+            results,
+            vec![
+                (Ok(()), vec!["a".to_string()]),
+                (Err(6), vec![]),
+                (Ok(()), vec![]),
+            ]
+        );
+        let err = RegexParser::new_owned(String::from("a"), String::from("gg"))
+            .err()
+            .unwrap();
+        assert_eq!((err.idx, err.space), (1, IndexSpace::Flags));
+        // the parser keeps working after its inputs are dropped
+        let (pattern, flags) = (String::from("(?<a>b)c"), String::from("u"));
+        let mut parser = RegexParser::new_owned(pattern.clone(), flags.clone()).unwrap();
+        drop((pattern, flags));
+        parser.validate().unwrap();
+        parser.revalidate_region(r"(?<a>b)\k<a>", 7).unwrap();
+        assert_eq!(parser.group_names(), vec!["a"]);
+        parser.reset_with("(?<b>c)", "g").unwrap();
+        parser.validate().unwrap();
+        assert_eq!(parser.group_names(), vec!["b"]);
+    }
+
+    #[test]
+    fn max_depth() {
+        // without a limit these would overflow the stack
+        for re in [
+            format!("/{}a{}/", "(".repeat(5000), ")".repeat(5000)),
+            format!("/{}a/", "(?=".repeat(5000)),
+            format!("/{}a{}/v", "[".repeat(5000), "]".repeat(5000)),
+        ]
+        .iter()
+        {
+            let err = run_test(re).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::TooDeeplyNested);
+            assert_eq!(err.msg, "Expression too deeply nested");
         }
+        let nested = |depth: usize| format!("/{}a{}/", "(?:".repeat(depth), ")".repeat(depth));
+        run_test(&nested(DEFAULT_MAX_DEPTH)).unwrap();
+        let err = run_test(&nested(DEFAULT_MAX_DEPTH + 1)).unwrap_err();
+        assert_eq!(err.idx, (DEFAULT_MAX_DEPTH + 1) * 3);
+        let re = nested(10);
+        let mut parser = RegexParser::with_max_depth(&re, 9).unwrap();
+        parser.validate().unwrap_err();
+        let mut parser = RegexParser::with_max_depth(&re, 10).unwrap();
+        parser.validate().unwrap();
+        // a region revalidation counts the groups around the region
+        let mut parser = RegexParser::with_max_depth("/(?:(?:a))/", 2).unwrap();
+        parser.validate().unwrap();
+        let err = parser.revalidate_region("(?:(?:(?:a)))", 6).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TooDeeplyNested);
+        parser.revalidate_region("(?:(?:b))", 6).unwrap();
+        // a class before the region is closed again
+        let mut parser = RegexParser::with_max_depth("/[a](?:b)/", 1).unwrap();
+        parser.validate().unwrap();
+        parser.revalidate_region("[a](?:bc)", 7).unwrap();
+        let err = parser.revalidate_region("[a](?:(?:b))", 7).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TooDeeplyNested);
     }
+
     #[test]
-    fn long_or_sequences() {
-        run_test(r#"/((?:[^BEGHLMOSWYZabcdhmswyz']+)|(?:'(?:[^']|'')*')|(?:G{1,5}|y{1,4}|Y{1,4}|M{1,5}|L{1,5}|w{1,2}|W{1}|d{1,2}|E{1,6}|c{1,6}|a{1,5}|b{1,5}|B{1,5}|h{1,2}|H{1,2}|m{1,2}|s{1,2}|S{1,3}|z{1,4}|Z{1,5}|O{1,4}))([\s\S]*)/"#).unwrap();
+    fn cloned_parser() {
+        let mut parser = RegexParser::new("/(?<a>b)|c/").unwrap();
+        parser.validate().unwrap();
+        let mut attempt = parser.clone();
+        attempt.revalidate_region("(?<a>b)(?<a>", 1).unwrap_err();
+        assert_eq!(parser.group_names(), vec!["a"]);
+        assert_eq!(parser.top_level_branch_count(), 2);
+        let mut attempt = parser.clone();
+        attempt.revalidate_region("(?<d>e)", 1).unwrap();
+        assert_eq!(attempt.group_names(), vec!["d"]);
+        assert_eq!(parser.group_names(), vec!["a"]);
+        // a clone before validating starts from the same position
+        let mut first = RegexParser::new("/a{1/u").unwrap();
+        let mut second = first.clone();
+        let first = first.validate().unwrap_err();
+        let second = second.validate().unwrap_err();
+        assert_eq!((first.idx, first.kind), (second.idx, second.kind));
+    }
+
+    #[test]
+    fn parser_builder() {
+        let builder = RegexParserBuilder::new().max_depth(1);
+        let err = builder.build("/((a))/").unwrap().validate().unwrap_err();
+        assert_eq!(err.kind, ErrorKind::TooDeeplyNested);
+        builder.build("/(a)/").unwrap().validate().unwrap();
+        let builder = builder.max_depth(2).strict(true);
+        builder.build("/((a))/").unwrap().validate().unwrap();
+        builder.build(r"/\2(a)/").unwrap().validate().unwrap_err();
+        let err = RegexParserBuilder::new()
+            .version(EcmaVersion::Es2015)
+            .build("/a/s")
+            .err()
+            .unwrap();
+        assert_eq!((err.idx, err.space), (3, IndexSpace::Literal));
+        let mut parser = RegexParserBuilder::new()
+            .collect_warnings(true)
+            .build("/a||b/")
+            .unwrap();
+        assert_eq!(parser.validate_detailed().unwrap().len(), 1);
+        let builder = RegexParserBuilder::from_options(ParserOptions {
+            strict: true,
+            ..ParserOptions::default()
+        });
+        assert!(builder.options().strict);
+        assert_eq!(builder.options().max_depth, None);
+    }
+
+    #[test]
+    fn malformed_tokens_are_internal_errors() {
+        let token = |kind, span| Token { kind, span };
+        let cases: &[(&str, &[Token], usize)] = &[
+            ("+", &[token(TokenKind::Quantifier, (0, 1))], 0),
+            (
+                "a+",
+                &[
+                    token(TokenKind::Literal, (0, 1)),
+                    token(TokenKind::Quantifier, (1, 2)),
+                ],
+                1,
+            ),
+            (
+                "[a-",
+                &[
+                    token(TokenKind::ClassOpen, (0, 1)),
+                    token(TokenKind::Literal, (1, 2)),
+                    token(TokenKind::ClassRange, (2, 3)),
+                ],
+                3,
+            ),
+            (
+                "[|",
+                &[
+                    token(TokenKind::ClassOpen, (0, 1)),
+                    token(TokenKind::Alternation, (1, 2)),
+                ],
+                1,
+            ),
+        ];
+        for (pattern, tokens, idx) in cases.iter() {
+            let err = ast::Builder::new(pattern, tokens, &[], &[])
+                .build()
+                .unwrap_err();
+            assert_eq!(
+                (err.kind, err.idx),
+                (ErrorKind::Internal, *idx),
+                "{}",
+                pattern
+            );
+        }
     }
 
     fn run_test(regex: &str) -> Result<(), Error> {
@@ -1379,4 +6829,30 @@ mod tests {
         parser.validate()?;
         Ok(())
     }
+
+    fn run_warnings_test(regex: &str) -> Result<Vec<Warning>, Error> {
+        let _ = pretty_env_logger::try_init();
+        let mut parser = RegexParser::with_options(
+            regex,
+            ParserOptions {
+                collect_warnings: true,
+                ..ParserOptions::default()
+            },
+        )?;
+        parser.validate()?;
+        Ok(parser.warnings().to_vec())
+    }
+
+    fn run_strict_test(regex: &str) -> Result<(), Error> {
+        let _ = pretty_env_logger::try_init();
+        let mut parser = RegexParser::with_options(
+            regex,
+            ParserOptions {
+                strict: true,
+                ..ParserOptions::default()
+            },
+        )?;
+        parser.validate()?;
+        Ok(())
+    }
 }
@@ -0,0 +1,620 @@
+//! A small backtracking matcher for the common subset of
+//! regular expressions, patterns that use back references,
+//! look around assertions or property escapes are reported
+//! as unsupported
+use crate::ast::*;
+use crate::{unicode, Error, ErrorKind, RegExFlags, RegexParser};
+use alloc::{rc::Rc, vec::Vec};
+
+/// Test if a regular expression literal matches anywhere in
+/// `haystack`. The `i`, `m` and `s` flags are respected, the
+/// rest have no effect on the result. Like JavaScript, the
+/// haystack is matched as UTF-16 code units unless the `u` or
+/// `v` flag is set
+///
+/// ```
+/// assert!(res_regex::is_match("/a+b/", "aaab").unwrap());
+/// assert!(!res_regex::is_match("/^a+b$/", "aaabc").unwrap());
+/// assert!(!res_regex::is_match("/^.$/", "😀").unwrap());
+/// assert!(res_regex::is_match("/^.$/u", "😀").unwrap());
+/// assert!(res_regex::is_match(r"/(a)\1/", "aa").is_err());
+/// ```
+pub fn is_match(pattern: &str, haystack: &str) -> Result<bool, Error> {
+    let mut parser = RegexParser::new(pattern)?;
+    let ast = parser.parse()?;
+    let flags = *parser.flags();
+    let unicode = flags.unicode || flags.unicode_sets;
+    check_supported(&ast.disjunction, unicode)?;
+    let matcher = Matcher {
+        input: if unicode {
+            haystack.chars().map(u32::from).collect()
+        } else {
+            haystack.encode_utf16().map(u32::from).collect()
+        },
+        flags,
+        unicode,
+    };
+    for start in 0..=matcher.input.len() {
+        if matcher.matches_at(&ast.disjunction, start) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Without the `u` flag a non-BMP character is two code units,
+/// `unicode` is `false` to reject a quantifier that would only
+/// apply to its trail surrogate or a class range ending at one
+fn check_supported(disjunction: &Disjunction, unicode: bool) -> Result<(), Error> {
+    for term in disjunction.alternatives.iter().flat_map(|a| &a.terms) {
+        match &term.kind {
+            TermKind::Atom(atom @ Atom::Char(_)) | TermKind::Atom(atom @ Atom::Escape(_))
+                if !unicode
+                    && term.quantifier.is_some()
+                    && atom_value(atom).is_some_and(|value| value > 0xFFFF) =>
+            {
+                return Err(Error::new(
+                    term.span.0,
+                    ErrorKind::Unsupported,
+                    "Unsupported: quantified non-BMP character without the u flag",
+                ))
+            }
+            TermKind::Assertion(Assertion {
+                span,
+                kind: AssertionKind::Lookahead { .. },
+            })
+            | TermKind::Assertion(Assertion {
+                span,
+                kind: AssertionKind::Lookbehind { .. },
+            }) => {
+                return Err(Error::new(
+                    span.0,
+                    ErrorKind::Unsupported,
+                    "Unsupported: look around assertion",
+                ))
+            }
+            TermKind::Atom(Atom::BackReference(back_ref)) => {
+                return Err(Error::new(
+                    back_ref.span.0,
+                    ErrorKind::Unsupported,
+                    "Unsupported: back reference",
+                ))
+            }
+            TermKind::Atom(Atom::Escape(escape)) => check_escape(escape)?,
+            TermKind::Atom(Atom::Class(class)) => {
+                if class.operation != ClassOperation::Union {
+                    return Err(Error::new(
+                        class.span.0,
+                        ErrorKind::Unsupported,
+                        "Unsupported: class set notation",
+                    ));
+                }
+                for item in &class.items {
+                    match item {
+                        ClassItem::Class(CharacterClass { span, .. })
+                        | ClassItem::Strings { span, .. } => {
+                            return Err(Error::new(
+                                span.0,
+                                ErrorKind::Unsupported,
+                                "Unsupported: class set notation",
+                            ))
+                        }
+                        ClassItem::Atom(ClassAtom::Escape(escape)) => check_escape(escape)?,
+                        ClassItem::Range { span, start, end } => {
+                            if !unicode
+                                && [start, end]
+                                    .iter()
+                                    .any(|atom| class_atom_value(atom).is_some_and(|v| v > 0xFFFF))
+                            {
+                                return Err(Error::new(
+                                    span.0,
+                                    ErrorKind::Unsupported,
+                                    "Unsupported: non-BMP class range without the u flag",
+                                ));
+                            }
+                            if let ClassAtom::Escape(escape) = start {
+                                check_escape(escape)?;
+                            }
+                            if let ClassAtom::Escape(escape) = end {
+                                check_escape(escape)?;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            TermKind::Atom(Atom::Group(group)) => {
+                if let GroupKind::Modifiers { .. } = group.kind {
+                    return Err(Error::new(
+                        group.span.0,
+                        ErrorKind::Unsupported,
+                        "Unsupported: modifier group",
+                    ));
+                }
+                check_supported(&group.disjunction, unicode)?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn check_escape(escape: &Escape) -> Result<(), Error> {
+    if let EscapeKind::Property { .. } = escape.kind {
+        return Err(Error::new(
+            escape.span.0,
+            ErrorKind::Unsupported,
+            "Unsupported: property escape",
+        ));
+    }
+    Ok(())
+}
+
+struct Matcher {
+    /// Code points with the `u` or `v` flag, UTF-16 code
+    /// units otherwise
+    input: Vec<u32>,
+    flags: RegExFlags,
+    unicode: bool,
+}
+
+/// Something left to match, `Terms` and `Repeat` hold the
+/// rest of the pattern after the current position
+enum Goal<'a> {
+    /// Each of these terms in order
+    Terms(&'a [Term]),
+    /// One occurrence of an atom
+    Atom(&'a Atom),
+    /// More iterations of a quantified atom, `count` have
+    /// matched so far
+    Repeat {
+        atom: &'a Atom,
+        quantifier: &'a Quantifier,
+        count: u64,
+    },
+    /// An iteration of a quantifier past its minimum that
+    /// started at this position, if nothing was consumed it
+    /// can't make progress so it is treated as a failure
+    Progress(usize),
+    /// A single code unit, the trail surrogate of a non-BMP
+    /// character without the `u` flag
+    Unit(u32),
+}
+
+/// A continuation, the goals that need to match after the
+/// current one. This is a persistent list so a choice point
+/// can keep the continuation it needs to resume from
+struct Cont<'a> {
+    goal: Goal<'a>,
+    next: Stack<'a>,
+}
+
+/// The goals left to match, `None` when the match is complete
+type Stack<'a> = Option<Rc<Cont<'a>>>;
+
+fn push<'a>(goal: Goal<'a>, next: Stack<'a>) -> Stack<'a> {
+    Some(Rc::new(Cont { goal, next }))
+}
+
+impl Matcher {
+    /// Attempt to match `disjunction` starting at `start`, this
+    /// keeps its own stack of choice points so the depth of the
+    /// native stack doesn't grow with the length of the input
+    fn matches_at(&self, disjunction: &Disjunction, start: usize) -> bool {
+        let mut choices = Vec::new();
+        let mut pos = start;
+        let mut cont = self.alternatives(disjunction, pos, None, &mut choices);
+        loop {
+            let ok = match &cont {
+                None => return true,
+                Some(node) => {
+                    let next = node.next.clone();
+                    match self.step(&node.goal, &mut pos, next, &mut choices) {
+                        Some(next) => {
+                            cont = next;
+                            true
+                        }
+                        None => false,
+                    }
+                }
+            };
+            if !ok {
+                match choices.pop() {
+                    Some((choice_pos, choice_cont)) => {
+                        pos = choice_pos;
+                        cont = choice_cont;
+                    }
+                    None => return false,
+                }
+            }
+        }
+    }
+    /// Continue with the first alternative of a disjunction,
+    /// the others are pushed as choice points
+    fn alternatives<'a>(
+        &self,
+        disjunction: &'a Disjunction,
+        pos: usize,
+        next: Stack<'a>,
+        choices: &mut Vec<(usize, Stack<'a>)>,
+    ) -> Stack<'a> {
+        for alt in disjunction.alternatives.iter().skip(1).rev() {
+            choices.push((pos, push(Goal::Terms(&alt.terms), next.clone())));
+        }
+        match disjunction.alternatives.first() {
+            Some(alt) => push(Goal::Terms(&alt.terms), next),
+            None => next,
+        }
+    }
+    /// Match a single goal at `pos`, returning the continuation
+    /// to resume with or `None` if it failed
+    fn step<'a>(
+        &self,
+        goal: &Goal<'a>,
+        pos: &mut usize,
+        next: Stack<'a>,
+        choices: &mut Vec<(usize, Stack<'a>)>,
+    ) -> Option<Stack<'a>> {
+        match *goal {
+            Goal::Terms(terms) => {
+                let (term, rest) = match terms.split_first() {
+                    Some(split) => split,
+                    None => return Some(next),
+                };
+                let next = push(Goal::Terms(rest), next);
+                match (&term.kind, &term.quantifier) {
+                    (TermKind::Assertion(assertion), _) => {
+                        if self.assertion(assertion, *pos) {
+                            Some(next)
+                        } else {
+                            None
+                        }
+                    }
+                    (TermKind::Atom(atom), None) => Some(push(Goal::Atom(atom), next)),
+                    (TermKind::Atom(atom), Some(quantifier)) => Some(push(
+                        Goal::Repeat {
+                            atom,
+                            quantifier,
+                            count: 0,
+                        },
+                        next,
+                    )),
+                }
+            }
+            Goal::Atom(Atom::Group(group)) => {
+                Some(self.alternatives(&group.disjunction, *pos, next, choices))
+            }
+            Goal::Atom(atom) => {
+                let unit = *self.input.get(*pos)?;
+                if let Some((lead, trail)) = self.surrogate_pair(atom) {
+                    if unit != lead {
+                        return None;
+                    }
+                    *pos += 1;
+                    return Some(push(Goal::Unit(trail), next));
+                }
+                if !self.atom_matches(atom, unit) {
+                    return None;
+                }
+                *pos += 1;
+                Some(next)
+            }
+            Goal::Unit(expected) => {
+                if self.input.get(*pos) != Some(&expected) {
+                    return None;
+                }
+                *pos += 1;
+                Some(next)
+            }
+            Goal::Progress(start) => {
+                if *pos == start {
+                    None
+                } else {
+                    Some(next)
+                }
+            }
+            Goal::Repeat {
+                atom,
+                quantifier,
+                count,
+            } => {
+                let again = |next| {
+                    push(
+                        Goal::Repeat {
+                            atom,
+                            quantifier,
+                            count: count + 1,
+                        },
+                        next,
+                    )
+                };
+                if count < quantifier.min {
+                    return Some(push(Goal::Atom(atom), again(next)));
+                }
+                if quantifier.max.map(|max| count >= max).unwrap_or(false) {
+                    return Some(next);
+                }
+                let iteration = push(
+                    Goal::Atom(atom),
+                    push(Goal::Progress(*pos), again(next.clone())),
+                );
+                // greedy quantifiers try another iteration before the
+                // continuation, lazy quantifiers try it after
+                if quantifier.greedy {
+                    choices.push((*pos, next));
+                    Some(iteration)
+                } else {
+                    choices.push((*pos, iteration));
+                    Some(next)
+                }
+            }
+        }
+    }
+    /// The UTF-16 surrogates of a literal non-BMP character when
+    /// the input is being matched as code units
+    fn surrogate_pair(&self, atom: &Atom) -> Option<(u32, u32)> {
+        if self.unicode {
+            return None;
+        }
+        let value = atom_value(atom).filter(|value| *value > 0xFFFF)? - 0x1_0000;
+        Some((0xD800 + (value >> 10), 0xDC00 + (value & 0x3FF)))
+    }
+
+    fn atom_matches(&self, atom: &Atom, unit: u32) -> bool {
+        match atom {
+            Atom::Char(c) => self.chars_eq(c.value as u32, unit),
+            Atom::Dot(_) => self.flags.dot_matches_new_line || !is_line_terminator(unit),
+            Atom::Escape(escape) => self.escape_matches(escape, unit),
+            Atom::Class(class) => {
+                class.items.iter().any(|item| self.item_matches(item, unit)) != class.negated
+            }
+            _ => false,
+        }
+    }
+
+    fn escape_matches(&self, escape: &Escape, unit: u32) -> bool {
+        match escape.kind {
+            EscapeKind::Character(value) => self.chars_eq(value, unit),
+            EscapeKind::Class(class) => {
+                let matches = match class.to_ascii_lowercase() {
+                    'd' => (0x30..=0x39).contains(&unit),
+                    'w' => self.is_word_char(unit),
+                    _ => is_white_space(unit),
+                };
+                matches != class.is_ascii_uppercase()
+            }
+            EscapeKind::Property { .. } => false,
+        }
+    }
+
+    fn item_matches(&self, item: &ClassItem, unit: u32) -> bool {
+        match item {
+            ClassItem::Atom(ClassAtom::Char(c)) => match self.surrogate_pair(&Atom::Char(*c)) {
+                // without `u` a non-BMP character in a class is its two surrogates
+                Some((lead, trail)) => unit == lead || unit == trail,
+                None => self.chars_eq(c.value as u32, unit),
+            },
+            ClassItem::Atom(ClassAtom::Escape(escape)) => self.escape_matches(escape, unit),
+            ClassItem::Range { start, end, .. } => {
+                let (start, end) = match (class_atom_value(start), class_atom_value(end)) {
+                    (Some(start), Some(end)) => (start, end),
+                    // a range with a class escape is a union of its parts
+                    _ => {
+                        return self.item_matches(&ClassItem::Atom(start.clone()), unit)
+                            || unit == '-' as u32
+                            || self.item_matches(&ClassItem::Atom(end.clone()), unit)
+                    }
+                };
+                let range = start..=end;
+                if range.contains(&unit) {
+                    return true;
+                }
+                if !self.flags.case_insensitive {
+                    return false;
+                }
+                // any character in the range that canonicalizes
+                // to the same thing as `unit`
+                let folds = unicode::simple_case_folds(unit);
+                if self.unicode {
+                    return folds.iter().any(|c| range.contains(&(*c as u32)));
+                }
+                let canonical = unicode::canonicalize_unit(unit);
+                folds
+                    .iter()
+                    .map(|c| *c as u32)
+                    .chain(core::iter::once(canonical))
+                    .any(|c| range.contains(&c) && unicode::canonicalize_unit(c) == canonical)
+            }
+            ClassItem::Class(_) | ClassItem::Strings { .. } => false,
+        }
+    }
+    /// `Canonicalize(value) == Canonicalize(unit)`
+    fn chars_eq(&self, value: u32, unit: u32) -> bool {
+        if value == unit {
+            return true;
+        }
+        if !self.flags.case_insensitive {
+            return false;
+        }
+        if self.unicode {
+            unicode::simple_case_folds(value)
+                .iter()
+                .any(|c| *c as u32 == unit)
+        } else {
+            unicode::canonicalize_unit(value) == unicode::canonicalize_unit(unit)
+        }
+    }
+    /// With the `i` and `u` flags `\w` also matches the long s
+    /// and the Kelvin sign since they fold to `s` and `k`
+    fn is_word_char(&self, unit: u32) -> bool {
+        is_word_char(unit)
+            || self.unicode && self.flags.case_insensitive && (unit == 0x17F || unit == 0x212A)
+    }
+
+    fn assertion(&self, assertion: &Assertion, pos: usize) -> bool {
+        let prev = pos.checked_sub(1).and_then(|i| self.input.get(i));
+        let next = self.input.get(pos);
+        match assertion.kind {
+            AssertionKind::Start => {
+                pos == 0 || self.flags.multi_line && prev.is_some_and(|c| is_line_terminator(*c))
+            }
+            AssertionKind::End => {
+                pos == self.input.len()
+                    || self.flags.multi_line && next.is_some_and(|c| is_line_terminator(*c))
+            }
+            AssertionKind::WordBoundary | AssertionKind::NotWordBoundary => {
+                let boundary = prev.is_some_and(|c| self.is_word_char(*c))
+                    != next.is_some_and(|c| self.is_word_char(*c));
+                boundary == (assertion.kind == AssertionKind::WordBoundary)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The code point of a literal character or character escape
+fn atom_value(atom: &Atom) -> Option<u32> {
+    match atom {
+        Atom::Char(c) => Some(c.value as u32),
+        Atom::Escape(Escape {
+            kind: EscapeKind::Character(value),
+            ..
+        }) => Some(*value),
+        _ => None,
+    }
+}
+
+fn class_atom_value(atom: &ClassAtom) -> Option<u32> {
+    match atom {
+        ClassAtom::Char(c) => Some(c.value as u32),
+        ClassAtom::Escape(Escape {
+            kind: EscapeKind::Character(value),
+            ..
+        }) => Some(*value),
+        _ => None,
+    }
+}
+
+fn is_word_char(unit: u32) -> bool {
+    core::char::from_u32(unit).is_some_and(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+fn is_line_terminator(unit: u32) -> bool {
+    matches!(unit, 0x0A | 0x0D | 0x2028 | 0x2029)
+}
+
+/// The `WhiteSpace` and `LineTerminator` code points, the
+/// characters in the `Space_Separator` category are listed
+/// out since `char::is_whitespace` includes more than those
+fn is_white_space(unit: u32) -> bool {
+    is_line_terminator(unit)
+        || matches!(
+            unit,
+            0x09 | 0x0B | 0x0C | 0x20 | 0xA0 | 0x1680 | 0x2000
+                ..=0x200A | 0x202F | 0x205F | 0x3000 | 0xFEFF
+        )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn simple_matches() {
+        assert!(is_match("/a+b/", "aaab").unwrap());
+        assert!(!is_match("/a+b/", "ac").unwrap());
+        assert!(is_match("/^(ab|cd){2}$/", "abcd").unwrap());
+        assert!(!is_match("/^(ab|cd){2}$/", "abcde").unwrap());
+        assert!(is_match(r"/\d{3}-\w+/", "call 555-abc_1").unwrap());
+        assert!(is_match(r"/[^\s]x/", " yx").unwrap());
+        assert!(!is_match(r"/[^\s]x/", " x").unwrap());
+        assert!(is_match(r"/[a-c\-]z/", "-z").unwrap());
+        assert!(is_match(r"/a.?c/", "ac").unwrap());
+        assert!(!is_match(r"/a.c/", "a\nc").unwrap());
+        assert!(is_match(r"/a.c/s", "a\nc").unwrap());
+        assert!(is_match(r"/^b/m", "a\nb").unwrap());
+        assert!(!is_match(r"/^b/", "a\nb").unwrap());
+        assert!(is_match(r"/\bcat\b/", "a cat!").unwrap());
+        assert!(!is_match(r"/\Bcat/", "a cat").unwrap());
+        assert!(is_match(r"/HELLO/i", "hello").unwrap());
+        assert!(is_match(r"/[A-Z]+/i", "hello").unwrap());
+        assert!(is_match(r"/(a*)*b/", "aab").unwrap());
+        assert!(is_match(r"/a{2,3}?$/", "aaa").unwrap());
+        assert!(is_match(r"/\u{1F600}/u", "😀").unwrap());
+        assert!(is_match(r"//", "").unwrap());
+    }
+
+    #[test]
+    fn long_input() {
+        let long = "a".repeat(100_000);
+        assert!(!is_match("/^a+b/", &long).unwrap());
+        assert!(is_match("/^(?:a|b)*$/", &long).unwrap());
+        assert!(is_match("/^a+?$/", &long).unwrap());
+    }
+
+    #[test]
+    fn white_space() {
+        for ch in &[
+            '\t', '\u{B}', '\u{C}', ' ', '\u{A0}', '\u{1680}', '\u{2000}', '\u{200A}', '\u{202F}',
+            '\u{205F}', '\u{3000}', '\u{FEFF}', '\n', '\r', '\u{2028}', '\u{2029}',
+        ] {
+            assert!(is_match(r"/^\s$/", &ch.to_string()).unwrap(), "{:?}", ch);
+        }
+        for ch in &['\u{85}', '\u{180E}', '\u{200B}', 'a'] {
+            assert!(!is_match(r"/^\s$/", &ch.to_string()).unwrap(), "{:?}", ch);
+            assert!(is_match(r"/^\S$/", &ch.to_string()).unwrap(), "{:?}", ch);
+        }
+    }
+
+    #[test]
+    fn code_units() {
+        assert!(!is_match("/^.$/", "😀").unwrap());
+        assert!(is_match("/^..$/", "😀").unwrap());
+        assert!(is_match("/^.$/u", "😀").unwrap());
+        assert!(is_match("/^😀$/", "😀").unwrap());
+        assert!(is_match(r"/^\uD83D\uDE00$/", "😀").unwrap());
+        assert!(is_match(r"/^[😀][😀]$/", "😀").unwrap());
+        assert!(!is_match(r"/^[😀]$/u", "\u{1F601}").unwrap());
+        assert!(is_match(r"/^[^a]$/u", "😀").unwrap());
+        assert!(!is_match(r"/^[^a]$/", "😀").unwrap());
+        for re in &["/😀+/", "/[😀-😂]/"] {
+            let err = is_match(re, "😀").unwrap_err();
+            assert_eq!(err.kind, ErrorKind::Unsupported, "{}", re);
+        }
+        assert!(is_match("/^😀+$/u", "😀😀").unwrap());
+    }
+
+    #[test]
+    fn canonicalize() {
+        assert!(!is_match(r"/\u212A/i", "k").unwrap());
+        assert!(!is_match(r"/\u212A/i", "K").unwrap());
+        assert!(is_match(r"/\u212A/iu", "k").unwrap());
+        assert!(is_match(r"/\u212A/iv", "K").unwrap());
+        assert!(!is_match(r"/s/i", "\u{17F}").unwrap());
+        assert!(is_match(r"/s/iu", "\u{17F}").unwrap());
+        assert!(is_match(r"/σ/i", "ς").unwrap());
+        assert!(is_match(r"/[a-z]/iu", "\u{212A}").unwrap());
+        assert!(!is_match(r"/[a-z]/i", "\u{212A}").unwrap());
+        assert!(is_match(r"/[\u212A]/i", "\u{212A}").unwrap());
+        assert!(is_match(r"/[α-ω]/i", "Σ").unwrap());
+        assert!(is_match(r"/^\w$/iu", "\u{17F}").unwrap());
+        assert!(!is_match(r"/^\w$/i", "\u{17F}").unwrap());
+        assert!(!is_match(r"/ß/i", "SS").unwrap());
+    }
+
+    #[test]
+    fn unsupported() {
+        for re in &[
+            r"/(a)\1/",
+            "/a(?=b)/",
+            "/(?<!a)b/",
+            r"/\p{L}/u",
+            r"/[\P{L}]/u",
+            r"/[[a]b]/v",
+            r"/[\w--_]/v",
+        ] {
+            let err = is_match(re, "ab").unwrap_err();
+            assert!(err.msg.starts_with("Unsupported"), "{}", re);
+        }
+    }
+}
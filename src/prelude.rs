@@ -0,0 +1,18 @@
+//! Re-exports of the types most commonly needed
+//! to validate a regular expression
+//!
+//! ```
+//! use res_regex::prelude::*;
+//!
+//! let mut parser = RegexParser::new(r"/(?<year>\d{4})-(\d{2})/u").unwrap();
+//! parser.validate().unwrap();
+//! assert_eq!(parser.capture_count(), 2);
+//! ```
+pub use crate::ast::Pattern;
+pub use crate::{
+    flag_description, is_match, parse_flags_const, validate, validate_class, validate_parts,
+    validate_with_fallback, BackRef, DecimalEscape, DecimalResolution, EcmaVersion, Error,
+    ErrorKind, EscapeInfo, GroupInfo, IndexSpace, ParserOptions, QuantifierInfo, RegExFlags,
+    RegexParser, RegexReport, Token, TokenIter, TokenKind, UnicodeVersion, Warning,
+    DEFAULT_MAX_DEPTH, VALID_FLAGS,
+};
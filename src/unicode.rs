@@ -1,4 +1,28 @@
-use crate::unicode_tables::{general_category::GC, script_values::SCRIPT, GC_AND_BP};
+use crate::unicode_tables::{
+    case_folding::CASE_FOLDING_SIMPLE,
+    general_category::{GC, GC_ALIASES},
+    script_values::{SCRIPT, SCRIPT_ALIASES, SCRIPT_INTRODUCED},
+    GC_AND_BP, PROPERTIES_OF_STRINGS,
+};
+use alloc::string::{String, ToString};
+
+/// A Unicode version, only the major and minor
+/// parts are tracked since property values are
+/// never added in a patch release
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnicodeVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl UnicodeVersion {
+    pub const fn new(major: u8, minor: u8) -> Self {
+        Self { major, minor }
+    }
+}
+
+/// The newest Unicode version the property tables include
+pub const SUPPORTED_UNICODE_VERSION: UnicodeVersion = UnicodeVersion::new(16, 0);
 
 /// Validate a `LoneUnicodePropertyNameOrValue`
 /// is a valid name or value
@@ -34,6 +58,79 @@ pub fn validate_name_and_value(name: &str, value: &str) -> bool {
     }
 }
 
+/// Validate a `UnicodePropertyName` and `UnicodePropertyValue`
+/// are correct for a specific Unicode version
+///
+/// Only the Script values are versioned, anything added
+/// after Unicode 12.1 will be rejected when `version`
+/// is older than the release that introduced it
+pub fn validate_name_and_value_at(name: &str, value: &str, version: UnicodeVersion) -> bool {
+    if !validate_name_and_value(name, value) {
+        return false;
+    }
+    if let Some(set) = validate_name(name) {
+        if core::ptr::eq(set, SCRIPT) {
+            if let Ok(idx) = SCRIPT_INTRODUCED.binary_search_by(|(n, _)| n.cmp(&value)) {
+                let (major, minor) = SCRIPT_INTRODUCED[idx].1;
+                return UnicodeVersion::new(major, minor) <= version;
+            }
+        }
+    }
+    true
+}
+
+/// Check if a lone name is one of the properties of strings,
+/// these are only valid with the `v` flag
+///
+/// ex:
+/// ```js
+/// let re = /\p{RGI_Emoji}/v;
+/// ```
+pub fn is_property_of_strings(name: &str) -> bool {
+    PROPERTIES_OF_STRINGS.binary_search(&name).is_ok()
+}
+
+/// The other code points with the same simple case folding
+/// as `ch`, empty if there are none
+///
+/// ex:
+/// ```js
+/// /\u212A/iu.test("k"); // true, the Kelvin sign folds to k
+/// ```
+pub fn simple_case_folds(ch: u32) -> &'static [char] {
+    core::char::from_u32(ch)
+        .and_then(|ch| {
+            CASE_FOLDING_SIMPLE
+                .binary_search_by_key(&ch, |(c, _)| *c)
+                .ok()
+        })
+        .map(|idx| CASE_FOLDING_SIMPLE[idx].1)
+        .unwrap_or(&[])
+}
+
+/// `Canonicalize` for a UTF-16 code unit when the `i` flag is
+/// set without `u` or `v`, the unit is upper cased unless that
+/// would take more than one code unit or would map a non-ASCII
+/// character to an ASCII one
+///
+/// ex:
+/// ```js
+/// /\u212A/i.test("k"); // false, k is canonicalized to K
+/// ```
+pub fn canonicalize_unit(unit: u32) -> u32 {
+    let ch = match core::char::from_u32(unit) {
+        Some(ch) => ch,
+        None => return unit,
+    };
+    let mut upper = ch.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(upper), None) if (upper as u32) <= 0xFFFF && (unit < 128 || upper as u32 >= 128) => {
+            upper as u32
+        }
+        _ => unit,
+    }
+}
+
 /// Validate a name is `General_Category`, `gc`, `Script`,
 /// `Script_Extensions`, `sc` or `scx`. This will return
 /// Some with the correct list of possible values
@@ -48,6 +145,48 @@ pub fn validate_name(name: &str) -> Option<&[&str]> {
     }
 }
 
+/// Map a `UnicodePropertyName` alias to its canonical
+/// long form (`gc` -> `General_Category`), `None` if the
+/// name is not one of the valid property names
+pub fn canonical_name(name: &str) -> Option<&'static str> {
+    match name {
+        "General_Category" | "gc" => Some("General_Category"),
+        "Script" | "sc" => Some("Script"),
+        "Script_Extensions" | "scx" => Some("Script_Extensions"),
+        _ => None,
+    }
+}
+
+/// Map a `UnicodePropertyName` and `UnicodePropertyValue`
+/// to their canonical long forms
+///
+/// ```
+/// # use res_regex::canonicalize_property;
+/// assert_eq!(
+///     canonicalize_property("gc", "Lu"),
+///     Some(("General_Category".to_string(), "Uppercase_Letter".to_string()))
+/// );
+/// ```
+///
+/// This will return `None` if the name and value would
+/// fail `validate_name_and_value`
+pub fn canonicalize_property(name: &str, value: &str) -> Option<(String, String)> {
+    if !validate_name_and_value(name, value) {
+        return None;
+    }
+    let canonical = canonical_name(name)?;
+    let aliases = if canonical == "General_Category" {
+        GC_ALIASES
+    } else {
+        SCRIPT_ALIASES
+    };
+    let value = aliases
+        .binary_search_by(|(alias, _)| alias.cmp(&value))
+        .map(|idx| aliases[idx].1)
+        .unwrap_or(value);
+    Some((canonical.to_string(), value.to_string()))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -71,10 +210,96 @@ mod test {
         assert!(!validate_name_and_value("scx", "junk"));
     }
     #[test]
+    fn versioned_name_and_value() {
+        assert!(validate_name_and_value("Script", "Chorasmian"));
+        assert!(validate_name_and_value_at(
+            "Script",
+            "Chorasmian",
+            UnicodeVersion::new(13, 0)
+        ));
+        assert!(!validate_name_and_value_at(
+            "sc",
+            "Chrs",
+            UnicodeVersion::new(12, 1)
+        ));
+        assert!(validate_name_and_value_at(
+            "scx",
+            "Greek",
+            UnicodeVersion::new(12, 1)
+        ));
+        assert!(validate_name_and_value_at(
+            "gc",
+            "Lu",
+            UnicodeVersion::new(12, 1)
+        ));
+        for (value, (major, minor)) in SCRIPT_INTRODUCED {
+            let version = UnicodeVersion::new(*major, *minor);
+            assert!(version <= SUPPORTED_UNICODE_VERSION);
+            assert!(validate_name_and_value_at("Script", value, version));
+            assert!(!validate_name_and_value_at(
+                "Script",
+                value,
+                UnicodeVersion::new(major - 1, 0)
+            ));
+        }
+    }
+    #[test]
+    fn canonical_forms() {
+        assert_eq!(
+            canonicalize_property("gc", "Lu"),
+            Some((
+                "General_Category".to_string(),
+                "Uppercase_Letter".to_string()
+            ))
+        );
+        assert_eq!(
+            canonicalize_property("scx", "Grek"),
+            Some(("Script_Extensions".to_string(), "Greek".to_string()))
+        );
+        assert_eq!(
+            canonicalize_property("Script", "Greek"),
+            Some(("Script".to_string(), "Greek".to_string()))
+        );
+        assert_eq!(canonicalize_property("gc", "Grek"), None);
+        assert_eq!(canonicalize_property("junk", "Lu"), None);
+        for (alias, long) in GC_ALIASES {
+            assert!(GC.binary_search(alias).is_ok());
+            assert!(GC.binary_search(long).is_ok());
+        }
+        for (alias, long) in SCRIPT_ALIASES {
+            assert!(SCRIPT.binary_search(alias).is_ok());
+            assert!(SCRIPT.binary_search(long).is_ok());
+        }
+    }
+    #[test]
     fn name_or_value() {
         for value in GC_AND_BP {
             assert!(validate_name_or_value(value));
         }
         assert!(!validate_name_or_value("junk"));
     }
+    #[test]
+    fn case_folding() {
+        assert_eq!(simple_case_folds('K' as u32), &['k', '\u{212A}']);
+        assert_eq!(simple_case_folds('\u{3C2}' as u32), &['\u{3A3}', '\u{3C3}']);
+        assert!(simple_case_folds('1' as u32).is_empty());
+        assert!(simple_case_folds(0xD800).is_empty());
+        assert_eq!(canonicalize_unit('k' as u32), 'K' as u32);
+        assert_eq!(canonicalize_unit(0x212A), 0x212A);
+        // long s upper cases to S, which is ASCII
+        assert_eq!(canonicalize_unit(0x17F), 0x17F);
+        // sharp s upper cases to SS
+        assert_eq!(canonicalize_unit(0xDF), 0xDF);
+        assert_eq!(canonicalize_unit(0xD800), 0xD800);
+    }
+    #[test]
+    fn properties_of_strings() {
+        for name in PROPERTIES_OF_STRINGS {
+            assert!(is_property_of_strings(name));
+            // these aren't valid without the `v` flag
+            assert!(!validate_name_or_value(name));
+        }
+        assert!(!is_property_of_strings("Emoji"));
+        assert!(!is_property_of_strings("rgi_emoji"));
+    }
 }
@@ -1,3 +1,4 @@
+pub mod case_folding;
 pub mod general_category;
 pub mod script_values;
 
@@ -180,3 +181,16 @@ pub static GC_AND_BP: &[&str] = &[
     "punct",
     "space",
 ];
+
+/// The ordered names of the properties of strings,
+/// these can only be used (and never negated) with
+/// the `v` flag
+pub static PROPERTIES_OF_STRINGS: &[&str] = &[
+    "Basic_Emoji",
+    "Emoji_Keycap_Sequence",
+    "RGI_Emoji",
+    "RGI_Emoji_Flag_Sequence",
+    "RGI_Emoji_Modifier_Sequence",
+    "RGI_Emoji_Tag_Sequence",
+    "RGI_Emoji_ZWJ_Sequence",
+];
@@ -45,17 +45,23 @@ pub static SCRIPT: &[&str] = &[
     "Cham",
     "Cher",
     "Cherokee",
+    "Chorasmian",
+    "Chrs",
     "Common",
     "Copt",
     "Coptic",
+    "Cpmn",
     "Cprt",
     "Cuneiform",
     "Cypriot",
+    "Cypro_Minoan",
     "Cyrillic",
     "Cyrl",
     "Deseret",
     "Deva",
     "Devanagari",
+    "Diak",
+    "Dives_Akuru",
     "Dogr",
     "Dogra",
     "Dsrt",
@@ -69,6 +75,8 @@ pub static SCRIPT: &[&str] = &[
     "Elymaic",
     "Ethi",
     "Ethiopic",
+    "Gara",
+    "Garay",
     "Geor",
     "Georgian",
     "Glag",
@@ -83,9 +91,11 @@ pub static SCRIPT: &[&str] = &[
     "Grek",
     "Gujarati",
     "Gujr",
+    "Gukh",
     "Gunjala_Gondi",
     "Gurmukhi",
     "Guru",
+    "Gurung_Khema",
     "Han",
     "Hang",
     "Hangul",
@@ -115,15 +125,20 @@ pub static SCRIPT: &[&str] = &[
     "Kana",
     "Kannada",
     "Katakana",
+    "Kawi",
     "Kayah_Li",
     "Khar",
     "Kharoshthi",
+    "Khitan_Small_Script",
     "Khmer",
     "Khmr",
     "Khoj",
     "Khojki",
     "Khudawadi",
+    "Kirat_Rai",
+    "Kits",
     "Knda",
+    "Krai",
     "Kthi",
     "Lana",
     "Lao",
@@ -177,6 +192,8 @@ pub static SCRIPT: &[&str] = &[
     "Myanmar",
     "Mymr",
     "Nabataean",
+    "Nag_Mundari",
+    "Nagm",
     "Nand",
     "Nandinagari",
     "Narb",
@@ -191,6 +208,7 @@ pub static SCRIPT: &[&str] = &[
     "Ogam",
     "Ogham",
     "Ol_Chiki",
+    "Ol_Onal",
     "Olck",
     "Old_Hungarian",
     "Old_Italic",
@@ -200,6 +218,8 @@ pub static SCRIPT: &[&str] = &[
     "Old_Sogdian",
     "Old_South_Arabian",
     "Old_Turkic",
+    "Old_Uyghur",
+    "Onao",
     "Oriya",
     "Orkh",
     "Orya",
@@ -207,6 +227,7 @@ pub static SCRIPT: &[&str] = &[
     "Osge",
     "Osma",
     "Osmanya",
+    "Ougr",
     "Pahawh_Hmong",
     "Palm",
     "Palmyrene",
@@ -254,6 +275,8 @@ pub static SCRIPT: &[&str] = &[
     "Soyombo",
     "Sund",
     "Sundanese",
+    "Sunu",
+    "Sunuwar",
     "Sylo",
     "Syloti_Nagri",
     "Syrc",
@@ -271,6 +294,7 @@ pub static SCRIPT: &[&str] = &[
     "Tamil",
     "Taml",
     "Tang",
+    "Tangsa",
     "Tangut",
     "Tavt",
     "Telu",
@@ -285,16 +309,26 @@ pub static SCRIPT: &[&str] = &[
     "Tifinagh",
     "Tirh",
     "Tirhuta",
+    "Tnsa",
+    "Todhri",
+    "Todr",
+    "Toto",
+    "Tulu_Tigalari",
+    "Tutg",
     "Ugar",
     "Ugaritic",
     "Vai",
     "Vaii",
+    "Vith",
+    "Vithkuqi",
     "Wancho",
     "Wara",
     "Warang_Citi",
     "Wcho",
     "Xpeo",
     "Xsux",
+    "Yezi",
+    "Yezidi",
     "Yi",
     "Yiii",
     "Zanabazar_Square",
@@ -302,3 +336,212 @@ pub static SCRIPT: &[&str] = &[
     "Zinh",
     "Zyyy",
 ];
+
+/// The Script values (names and aliases) added after
+/// Unicode 12.1 along with the version that introduced them,
+/// ordered by name
+pub static SCRIPT_INTRODUCED: &[(&str, (u8, u8))] = &[
+    ("Chorasmian", (13, 0)),
+    ("Chrs", (13, 0)),
+    ("Cpmn", (14, 0)),
+    ("Cypro_Minoan", (14, 0)),
+    ("Diak", (13, 0)),
+    ("Dives_Akuru", (13, 0)),
+    ("Gara", (16, 0)),
+    ("Garay", (16, 0)),
+    ("Gukh", (16, 0)),
+    ("Gurung_Khema", (16, 0)),
+    ("Kawi", (15, 0)),
+    ("Khitan_Small_Script", (13, 0)),
+    ("Kirat_Rai", (16, 0)),
+    ("Kits", (13, 0)),
+    ("Krai", (16, 0)),
+    ("Nag_Mundari", (15, 0)),
+    ("Nagm", (15, 0)),
+    ("Ol_Onal", (16, 0)),
+    ("Old_Uyghur", (14, 0)),
+    ("Onao", (16, 0)),
+    ("Ougr", (14, 0)),
+    ("Sunu", (16, 0)),
+    ("Sunuwar", (16, 0)),
+    ("Tangsa", (14, 0)),
+    ("Tnsa", (14, 0)),
+    ("Todhri", (16, 0)),
+    ("Todr", (16, 0)),
+    ("Toto", (14, 0)),
+    ("Tulu_Tigalari", (16, 0)),
+    ("Tutg", (16, 0)),
+    ("Vith", (14, 0)),
+    ("Vithkuqi", (14, 0)),
+    ("Yezi", (13, 0)),
+    ("Yezidi", (13, 0)),
+];
+
+/// The Script value aliases mapped to their
+/// canonical long names, ordered by alias
+pub static SCRIPT_ALIASES: &[(&str, &str)] = &[
+    ("Adlm", "Adlam"),
+    ("Aghb", "Caucasian_Albanian"),
+    ("Arab", "Arabic"),
+    ("Armi", "Imperial_Aramaic"),
+    ("Armn", "Armenian"),
+    ("Avst", "Avestan"),
+    ("Bali", "Balinese"),
+    ("Bamu", "Bamum"),
+    ("Bass", "Bassa_Vah"),
+    ("Batk", "Batak"),
+    ("Beng", "Bengali"),
+    ("Bhks", "Bhaiksuki"),
+    ("Bopo", "Bopomofo"),
+    ("Brah", "Brahmi"),
+    ("Brai", "Braille"),
+    ("Bugi", "Buginese"),
+    ("Buhd", "Buhid"),
+    ("Cakm", "Chakma"),
+    ("Cans", "Canadian_Aboriginal"),
+    ("Cari", "Carian"),
+    ("Cher", "Cherokee"),
+    ("Chrs", "Chorasmian"),
+    ("Copt", "Coptic"),
+    ("Cpmn", "Cypro_Minoan"),
+    ("Cprt", "Cypriot"),
+    ("Cyrl", "Cyrillic"),
+    ("Deva", "Devanagari"),
+    ("Diak", "Dives_Akuru"),
+    ("Dogr", "Dogra"),
+    ("Dsrt", "Deseret"),
+    ("Dupl", "Duployan"),
+    ("Egyp", "Egyptian_Hieroglyphs"),
+    ("Elba", "Elbasan"),
+    ("Elym", "Elymaic"),
+    ("Ethi", "Ethiopic"),
+    ("Gara", "Garay"),
+    ("Geor", "Georgian"),
+    ("Glag", "Glagolitic"),
+    ("Gong", "Gunjala_Gondi"),
+    ("Gonm", "Masaram_Gondi"),
+    ("Goth", "Gothic"),
+    ("Gran", "Grantha"),
+    ("Grek", "Greek"),
+    ("Gujr", "Gujarati"),
+    ("Gukh", "Gurung_Khema"),
+    ("Guru", "Gurmukhi"),
+    ("Hang", "Hangul"),
+    ("Hani", "Han"),
+    ("Hano", "Hanunoo"),
+    ("Hatr", "Hatran"),
+    ("Hebr", "Hebrew"),
+    ("Hira", "Hiragana"),
+    ("Hluw", "Anatolian_Hieroglyphs"),
+    ("Hmng", "Pahawh_Hmong"),
+    ("Hmnp", "Nyiakeng_Puachue_Hmong"),
+    ("Hung", "Old_Hungarian"),
+    ("Ital", "Old_Italic"),
+    ("Java", "Javanese"),
+    ("Kali", "Kayah_Li"),
+    ("Kana", "Katakana"),
+    ("Khar", "Kharoshthi"),
+    ("Khmr", "Khmer"),
+    ("Khoj", "Khojki"),
+    ("Kits", "Khitan_Small_Script"),
+    ("Knda", "Kannada"),
+    ("Krai", "Kirat_Rai"),
+    ("Kthi", "Kaithi"),
+    ("Lana", "Tai_Tham"),
+    ("Laoo", "Lao"),
+    ("Latn", "Latin"),
+    ("Lepc", "Lepcha"),
+    ("Limb", "Limbu"),
+    ("Lina", "Linear_A"),
+    ("Linb", "Linear_B"),
+    ("Lyci", "Lycian"),
+    ("Lydi", "Lydian"),
+    ("Mahj", "Mahajani"),
+    ("Maka", "Makasar"),
+    ("Mand", "Mandaic"),
+    ("Mani", "Manichaean"),
+    ("Marc", "Marchen"),
+    ("Medf", "Medefaidrin"),
+    ("Mend", "Mende_Kikakui"),
+    ("Merc", "Meroitic_Cursive"),
+    ("Mero", "Meroitic_Hieroglyphs"),
+    ("Mlym", "Malayalam"),
+    ("Mong", "Mongolian"),
+    ("Mroo", "Mro"),
+    ("Mtei", "Meetei_Mayek"),
+    ("Mult", "Multani"),
+    ("Mymr", "Myanmar"),
+    ("Nagm", "Nag_Mundari"),
+    ("Nand", "Nandinagari"),
+    ("Narb", "Old_North_Arabian"),
+    ("Nbat", "Nabataean"),
+    ("Nkoo", "Nko"),
+    ("Nshu", "Nushu"),
+    ("Ogam", "Ogham"),
+    ("Olck", "Ol_Chiki"),
+    ("Onao", "Ol_Onal"),
+    ("Orkh", "Old_Turkic"),
+    ("Orya", "Oriya"),
+    ("Osge", "Osage"),
+    ("Osma", "Osmanya"),
+    ("Ougr", "Old_Uyghur"),
+    ("Palm", "Palmyrene"),
+    ("Pauc", "Pau_Cin_Hau"),
+    ("Perm", "Old_Permic"),
+    ("Phag", "Phags_Pa"),
+    ("Phli", "Inscriptional_Pahlavi"),
+    ("Phlp", "Psalter_Pahlavi"),
+    ("Phnx", "Phoenician"),
+    ("Plrd", "Miao"),
+    ("Prti", "Inscriptional_Parthian"),
+    ("Qaac", "Coptic"),
+    ("Qaai", "Inherited"),
+    ("Rjng", "Rejang"),
+    ("Rohg", "Hanifi_Rohingya"),
+    ("Runr", "Runic"),
+    ("Samr", "Samaritan"),
+    ("Sarb", "Old_South_Arabian"),
+    ("Saur", "Saurashtra"),
+    ("Sgnw", "SignWriting"),
+    ("Shaw", "Shavian"),
+    ("Shrd", "Sharada"),
+    ("Sidd", "Siddham"),
+    ("Sind", "Khudawadi"),
+    ("Sinh", "Sinhala"),
+    ("Sogd", "Sogdian"),
+    ("Sogo", "Old_Sogdian"),
+    ("Sora", "Sora_Sompeng"),
+    ("Soyo", "Soyombo"),
+    ("Sund", "Sundanese"),
+    ("Sunu", "Sunuwar"),
+    ("Sylo", "Syloti_Nagri"),
+    ("Syrc", "Syriac"),
+    ("Tagb", "Tagbanwa"),
+    ("Takr", "Takri"),
+    ("Tale", "Tai_Le"),
+    ("Talu", "New_Tai_Lue"),
+    ("Taml", "Tamil"),
+    ("Tang", "Tangut"),
+    ("Tavt", "Tai_Viet"),
+    ("Telu", "Telugu"),
+    ("Tfng", "Tifinagh"),
+    ("Tglg", "Tagalog"),
+    ("Thaa", "Thaana"),
+    ("Tibt", "Tibetan"),
+    ("Tirh", "Tirhuta"),
+    ("Tnsa", "Tangsa"),
+    ("Todr", "Todhri"),
+    ("Tutg", "Tulu_Tigalari"),
+    ("Ugar", "Ugaritic"),
+    ("Vaii", "Vai"),
+    ("Vith", "Vithkuqi"),
+    ("Wara", "Warang_Citi"),
+    ("Wcho", "Wancho"),
+    ("Xpeo", "Old_Persian"),
+    ("Xsux", "Cuneiform"),
+    ("Yezi", "Yezidi"),
+    ("Yiii", "Yi"),
+    ("Zanb", "Zanabazar_Square"),
+    ("Zinh", "Inherited"),
+    ("Zyyy", "Common"),
+];
@@ -0,0 +1,119 @@
+//! Feed the parser a large corpus of generated input and make
+//! sure every entry point returns (`Ok` or `Err`) instead of
+//! panicking, this runs the same checks as the fuzz target
+//! without needing `cargo fuzz`
+use res_regex::{is_match, validate_class, validate_with_fallback, ParserOptions, RegexParser};
+
+/// Pieces that are likely to reach the interesting parts of the parser
+static PIECES: &[&str] = &[
+    "/", "\\", "(", ")", "[", "]", "{", "}", "?", "*", "+", "|", "^", "$", ".", "-", "&", "<", ">",
+    "=", "!", ":", ",", "a", "k", "p", "u", "x", "c", "q", "0", "1", "8", "é", "😀", "\u{2028}",
+    "g", "i", "v", "{2,1}", "\\u{", "\\uD800", "\\k<", "(?<", "(?<=", "[\\q{", "&&", "--", "\\p{",
+];
+
+/// A small xorshift generator so the corpus is the same every run
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Either random bytes (invalid UTF-8 is replaced, so multi byte
+/// characters end up next to the delimiters) or random pieces,
+/// usually wrapped in `/`s with some flags
+fn generate(rng: &mut Rng) -> String {
+    let mut body = String::new();
+    let len = rng.below(12);
+    if rng.below(2) == 0 {
+        let bytes: Vec<u8> = (0..len * 2).map(|_| rng.below(256) as u8).collect();
+        body.push_str(&String::from_utf8_lossy(&bytes));
+    } else {
+        for _ in 0..len {
+            body.push_str(PIECES[rng.below(PIECES.len())]);
+        }
+    }
+    match rng.below(4) {
+        0 => body,
+        1 => format!("/{}", body),
+        _ => format!(
+            "/{}/{}",
+            body,
+            ["", "u", "v", "gi", "uv", "é"][rng.below(6)]
+        ),
+    }
+}
+
+fn exercise(js: &str) {
+    if let Ok(mut parser) = RegexParser::new(js) {
+        let _ = parser.validate();
+        let _ = parser.report();
+        let _ = parser.literal_prefixes();
+        let _ = parser.decimal_escapes();
+        let _ = parser.parse().map(|pattern| pattern.describe());
+    }
+    for flags in ["", "u", "v"].iter() {
+        if let Ok(mut parser) = RegexParser::from_parts(js, flags) {
+            let _ = parser.validate();
+            let _ = parser.describe();
+            let _ = parser.parse().map(|pattern| pattern.to_string());
+        }
+        let _ = validate_class(js, !flags.is_empty());
+    }
+    let options = ParserOptions {
+        strict: true,
+        collect_warnings: true,
+        warn_legacy_octal: true,
+        warn_unbounded_lookbehind: true,
+        collect_flag_errors: true,
+        ..ParserOptions::default()
+    };
+    if let Ok(mut parser) = RegexParser::with_options(js, options) {
+        let _ = parser.validate();
+    }
+    let _ = validate_with_fallback(js);
+    let _ = is_match(js, "ab😀a\n");
+}
+
+#[test]
+fn generated_input_never_panics() {
+    let mut rng = Rng(0x5EED);
+    for _ in 0..20_000 {
+        let js = generate(&mut rng);
+        if let Err(e) = std::panic::catch_unwind(|| exercise(&js)) {
+            panic!("panicked on {:?}: {:?}", js, e.downcast_ref::<String>());
+        }
+    }
+}
+
+#[test]
+fn multi_byte_characters_around_delimiters() {
+    for js in [
+        "/é",
+        "é/",
+        "/é/",
+        "/😀/é",
+        "//😀",
+        "/\\😀/",
+        "/[😀-é]/u",
+        "/a/\u{FFFD}",
+        "/\u{FFFD}/",
+        "/(?<😀>a)/",
+        "/\\k<é>/",
+        "/a{é}/",
+        "/\\u{é}/u",
+        "/\\p{é}/u",
+        "/[\\q{é}]/v",
+    ]
+    .iter()
+    {
+        exercise(js);
+    }
+}